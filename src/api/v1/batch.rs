@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+
+use actix_web::{http::StatusCode, post, web, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::links::{collision, validate_rustlink};
+use crate::{
+    index::{overlay::Overlay, Links, RustlinkIndex},
+    oidc::identity::Identity,
+    rustlink::{Rustlink, REFERENCE_PREFIX},
+    state::AppState,
+    store::{Condition, Operation},
+    trash::TrashedRustlink,
+    util, RustlinkAlias,
+};
+
+/// etcd's default limit (`--max-txn-ops`) on the operations in a transaction.
+/// Batches making more writes are committed in chunks of at most this many.
+pub const MAX_TXN_OPS: usize = 128;
+
+/// Operations accepted in a single batch
+pub const MAX_BATCH_OPERATIONS: usize = 10_000;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// Create or replace the link at `alias`
+    Put {
+        alias: String,
+        link: Rustlink,
+        /// Replace an existing link whose alias shares the same canonical
+        /// form, but is spelled differently
+        #[serde(default)]
+        overwrite: bool,
+    },
+    Delete {
+        alias: String,
+    },
+    /// Move the link at `from` to `to`, which mustn't have a link yet
+    Rename {
+        from: String,
+        to: String,
+        /// Leave a deprecated link at `from` referring to `to`, so that it
+        /// keeps redirecting
+        #[serde(default)]
+        leave_pointer: bool,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    operations: Vec<BatchOperation>,
+    /// Only compute the changes the operations would make, without applying
+    /// them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// How the link at an alias changes over the whole batch, with `None` where
+/// there's no link
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BatchChange {
+    pub alias: String,
+    pub before: Option<Rustlink>,
+    pub after: Option<Rustlink>,
+}
+
+/// Why an operation can't be applied
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OperationError {
+    /// Index of the operation in the batch
+    pub operation: usize,
+    pub error: String,
+}
+
+/// A chunk of operations which couldn't be committed. None of its operations
+/// (nor any after it) were applied.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChunkFailure {
+    pub first_operation: usize,
+    pub last_operation: usize,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct BatchResult {
+    pub dry_run: bool,
+    pub changes: Vec<BatchChange>,
+    /// How many operations were applied, counting from the first
+    pub applied: usize,
+    /// The revision the last applied chunk was committed at
+    pub revision: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<OperationError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<ChunkFailure>,
+}
+
+/// The keys an operation writes, along with their new values (or `None` to
+/// delete them)
+#[derive(Debug, PartialEq)]
+pub(super) struct Step {
+    operation: usize,
+    writes: Vec<(String, Option<Vec<u8>>)>,
+}
+
+/// What a key is expected to hold when a chunk writing to it is committed, so
+/// that chunks aren't applied over concurrent changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Expected {
+    Absent,
+    Present,
+    ModRevision(i64),
+}
+
+impl Expected {
+    fn of(rustlink: Option<&Rustlink>) -> Self {
+        match rustlink {
+            Some(rustlink) => rustlink
+                .revision
+                .map_or(Expected::Present, Expected::ModRevision),
+            None => Expected::Absent,
+        }
+    }
+
+    fn condition(self, key: String) -> Condition {
+        match self {
+            Expected::Absent => Condition::Absent(key),
+            Expected::Present => Condition::Present(key),
+            Expected::ModRevision(revision) => Condition::ModRevision(key, revision),
+        }
+    }
+}
+
+pub(super) struct Plan {
+    pub(super) steps: Vec<Step>,
+    pub(super) changes: Vec<BatchChange>,
+    pub(super) expected: HashMap<String, Expected>,
+}
+
+/// Tracks the links a batch touches, as they were before it
+#[derive(Default)]
+struct Touched {
+    /// In the order they were first touched
+    before: Vec<(RustlinkAlias, Option<Rustlink>)>,
+    expected: HashMap<String, Expected>,
+}
+
+impl Touched {
+    fn touch(&mut self, rustlinks: &Overlay, canonical: &str) {
+        if self.before.iter().any(|(alias, _)| alias == canonical) {
+            return;
+        }
+        let existing = rustlinks.link(canonical);
+        self.expected.insert(
+            format!("{}{}", util::NAMESPACE, canonical),
+            Expected::of(existing),
+        );
+        self.before.push((canonical.to_string(), existing.cloned()));
+    }
+}
+
+/// Apply `operation` to the `simulated` links, returning the writes it makes
+fn apply(
+    data: &AppState,
+    simulated: &mut Overlay,
+    touched: &mut Touched,
+    operation: BatchOperation,
+    identity: Option<&Identity>,
+) -> Result<Vec<(String, Option<Vec<u8>>)>, String> {
+    let normalizations = &data.alias_normalization;
+
+    match operation {
+        BatchOperation::Put {
+            alias,
+            link,
+            overwrite,
+        } => {
+            if let Some(reserved) = data.reserved.check(&alias) {
+                return Err(format!(
+                    "Alias `{}` is reserved for {}",
+                    alias, reserved.reason
+                ));
+            }
+            if !overwrite && let Some(collision) = collision(data, simulated, &alias) {
+                return Err(collision);
+            }
+            let canonical = util::canonicalize_alias(&alias, normalizations);
+            let existing = simulated.link(&canonical).cloned();
+            let (rustlink, bytes) =
+                validate_rustlink(data, simulated, &alias, link, existing.as_ref(), identity)?;
+
+            touched.touch(simulated, &canonical);
+            simulated.insert(canonical, rustlink);
+            Ok(vec![(
+                util::alias_to_key(&alias, normalizations),
+                Some(bytes),
+            )])
+        }
+        BatchOperation::Delete { alias } => {
+            let canonical = util::canonicalize_alias(&alias, normalizations);
+
+            let Some(existing) = simulated.link(&canonical).cloned() else {
+                return Err(format!("No link exists for alias `{}`", alias));
+            };
+            let trashed = TrashedRustlink::new(existing, identity, Utc::now());
+            let bytes = serde_json::to_vec(&trashed).map_err(|e| e.to_string())?;
+
+            touched.touch(simulated, &canonical);
+            simulated.remove(&canonical);
+            Ok(vec![
+                (util::alias_to_key(&alias, normalizations), None),
+                (
+                    util::alias_to_trash_key(&alias, normalizations),
+                    Some(bytes),
+                ),
+            ])
+        }
+        BatchOperation::Rename {
+            from,
+            to,
+            leave_pointer,
+        } => {
+            if let Some(reserved) = data.reserved.check(&to) {
+                return Err(format!(
+                    "Alias `{}` is reserved for {}",
+                    to, reserved.reason
+                ));
+            }
+            let from_canonical = util::canonicalize_alias(&from, normalizations);
+            let to_canonical = util::canonicalize_alias(&to, normalizations);
+            let Some(existing) = simulated.link(&from_canonical).cloned() else {
+                return Err(format!("No link exists for alias `{}`", from));
+            };
+
+            // Renaming to another spelling of the same alias only changes
+            // how it's displayed
+            if to_canonical != from_canonical && simulated.link(&to_canonical).is_some() {
+                return Err(format!("Alias `{}` already has a link", to));
+            }
+            // Links referring to the old alias would be left dangling
+            if to_canonical != from_canonical && !leave_pointer {
+                let referrers = simulated.referrers(&from_canonical, normalizations);
+
+                if !referrers.is_empty() {
+                    return Err(format!(
+                        "Links {} refer to `{}`, leave a pointer behind or update them first",
+                        referrers
+                            .iter()
+                            .map(|referrer| format!("`{}`", referrer))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                        from
+                    ));
+                }
+            }
+            let (rustlink, bytes) = validate_rustlink(
+                data,
+                simulated,
+                &to,
+                existing.clone(),
+                Some(&existing),
+                identity,
+            )?;
+
+            touched.touch(simulated, &from_canonical);
+            touched.touch(simulated, &to_canonical);
+            simulated.remove(&from_canonical);
+            simulated.insert(to_canonical.clone(), rustlink);
+
+            let to_write = (util::alias_to_key(&to, normalizations), Some(bytes));
+            if to_canonical == from_canonical {
+                return Ok(vec![to_write]);
+            }
+            let from_write = match leave_pointer {
+                true => {
+                    let pointer = Rustlink {
+                        url: format!("{}{}", REFERENCE_PREFIX, to),
+                        description: Some(format!("Renamed to `{}`", to)),
+                        owners: existing.owners.clone(),
+                        deprecated: true,
+                        ..Default::default()
+                    };
+                    let (pointer, bytes) = validate_rustlink(
+                        data,
+                        simulated,
+                        &from,
+                        pointer,
+                        Some(&existing),
+                        identity,
+                    )?;
+                    simulated.insert(from_canonical, pointer);
+                    Some(bytes)
+                }
+                false => None,
+            };
+            Ok(vec![
+                (util::alias_to_key(&from, normalizations), from_write),
+                to_write,
+            ])
+        }
+    }
+}
+
+/// Validate `operations` one after the other, each seeing the links as the
+/// ones before it left them, returning the writes they make and how they
+/// change the links
+pub(super) fn plan(
+    data: &AppState,
+    rustlinks: &RustlinkIndex,
+    operations: Vec<BatchOperation>,
+    identity: Option<&Identity>,
+) -> Result<Plan, Vec<OperationError>> {
+    plan_over(data, Overlay::new(rustlinks), operations, identity)
+}
+
+/// Like [`plan`], starting from links already changed from the synced ones
+/// (e.g. with what the store has for them)
+pub(super) fn plan_over(
+    data: &AppState,
+    mut simulated: Overlay,
+    operations: Vec<BatchOperation>,
+    identity: Option<&Identity>,
+) -> Result<Plan, Vec<OperationError>> {
+    let mut touched = Touched::default();
+    let mut steps: Vec<Step> = vec![];
+    let mut errors: Vec<OperationError> = vec![];
+
+    for (index, operation) in operations.into_iter().enumerate() {
+        let step = apply(data, &mut simulated, &mut touched, operation, identity);
+
+        match step {
+            Ok(writes) => steps.push(Step {
+                operation: index,
+                writes,
+            }),
+            Err(error) => errors.push(OperationError {
+                operation: index,
+                error,
+            }),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let changes = touched
+        .before
+        .into_iter()
+        .filter_map(|(canonical, before)| {
+            let after = simulated.link(&canonical).cloned();
+            let alias = after
+                .as_ref()
+                .or(before.as_ref())
+                .and_then(|rustlink| rustlink.display_alias.clone())
+                .unwrap_or(canonical);
+
+            (before != after).then_some(BatchChange {
+                alias,
+                before,
+                after,
+            })
+        })
+        .collect();
+
+    Ok(Plan {
+        steps,
+        changes,
+        expected: touched.expected,
+    })
+}
+
+/// Split `steps` into chunks making at most `limit` writes, without splitting
+/// any step across chunks
+fn chunk(steps: Vec<Step>, limit: usize) -> Vec<Vec<Step>> {
+    let mut chunks: Vec<Vec<Step>> = vec![];
+    let mut writes = 0;
+
+    for step in steps {
+        match chunks.last_mut() {
+            Some(last) if writes + step.writes.len() <= limit => {
+                writes += step.writes.len();
+                last.push(step);
+            }
+            _ => {
+                writes = step.writes.len();
+                chunks.push(vec![step]);
+            }
+        }
+    }
+    chunks
+}
+
+/// Commit the writes of `steps` in chunks, stopping at the first which fails,
+/// recording how far it got in `result`
+pub(super) async fn commit(
+    data: &AppState,
+    steps: Vec<Step>,
+    mut expected: HashMap<String, Expected>,
+    result: &mut BatchResult,
+) -> StatusCode {
+    for chunk in chunk(steps, MAX_TXN_OPS) {
+        // A key can only be written once per transaction, so later writes to
+        // it within the chunk replace earlier ones
+        let mut writes: Vec<(String, Option<Vec<u8>>)> = vec![];
+
+        for (key, value) in chunk.iter().flat_map(|step| step.writes.iter().cloned()) {
+            writes.retain(|(existing, _)| *existing != key);
+            writes.push((key, value));
+        }
+        // Only links are expected to be unchanged, links moved into the trash
+        // replace whatever was deleted at their alias before
+        let conditions = writes
+            .iter()
+            .filter_map(|(key, _)| expected.get(key).map(|e| e.condition(key.clone())))
+            .collect();
+        let operations = writes
+            .iter()
+            .map(|(key, value)| match value {
+                Some(value) => Operation::Put(key.clone(), value.clone()),
+                None => Operation::Delete(key.clone()),
+            })
+            .collect();
+        let failure = |error: &str| ChunkFailure {
+            first_operation: chunk[0].operation,
+            last_operation: chunk[chunk.len() - 1].operation,
+            error: error.to_string(),
+        };
+
+        match data.store.txn(conditions, operations).await {
+            Ok(Some(revision)) => {
+                for (key, value) in &writes {
+                    let now = match value {
+                        Some(_) => Expected::ModRevision(revision),
+                        None => Expected::Absent,
+                    };
+                    expected.insert(key.clone(), now);
+                }
+                result.applied += chunk.len();
+                result.revision = Some(revision);
+            }
+            Ok(None) => {
+                result.failed = Some(failure("Links changed since the batch was validated"));
+                return StatusCode::CONFLICT;
+            }
+            Err(e) => {
+                eprintln!("Failed to commit batch to etcd: {:?}", e);
+                result.failed = Some(failure("Internal Server Error"));
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+        }
+    }
+    StatusCode::OK
+}
+
+/// Apply a batch of put, delete, and rename operations to links. They're
+/// validated together up front, so that nothing is written if any of them is
+/// invalid, then committed in a single transaction, or in consecutive chunks
+/// if there are more writes than etcd allows in one. Should a chunk fail,
+/// the operations before it stay applied, and the response says which didn't.
+#[post("/links:batch")]
+pub async fn batch_rustlinks(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    batch: web::Json<BatchRequest>,
+) -> impl Responder {
+    let BatchRequest {
+        operations,
+        dry_run,
+    } = batch.into_inner();
+
+    if operations.len() > MAX_BATCH_OPERATIONS {
+        return HttpResponse::BadRequest().body(format!(
+            "Batches can have at most {} operations",
+            MAX_BATCH_OPERATIONS
+        ));
+    }
+    let planned = plan(
+        &data,
+        &*data.rustlinks.read().await,
+        operations,
+        identity.as_ref(),
+    );
+    let Plan {
+        steps,
+        changes,
+        expected,
+    } = match planned {
+        Ok(plan) => plan,
+        Err(errors) => {
+            return HttpResponse::BadRequest().json(BatchResult {
+                dry_run,
+                changes: vec![],
+                applied: 0,
+                revision: None,
+                errors,
+                failed: None,
+            });
+        }
+    };
+    let mut result = BatchResult {
+        dry_run,
+        changes,
+        applied: 0,
+        revision: None,
+        errors: vec![],
+        failed: None,
+    };
+
+    if dry_run {
+        return HttpResponse::Ok().json(result);
+    }
+
+    let status = commit(&data, steps, expected, &mut result).await;
+    HttpResponse::build(status).json(result)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn step(operation: usize, writes: usize) -> Step {
+        Step {
+            operation,
+            writes: (0..writes)
+                .map(|write| (format!("{}/{}", operation, write), None))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn it_chunks_steps_without_splitting_them() {
+        let chunks = chunk(vec![step(0, 1), step(1, 2), step(2, 1), step(3, 2)], 3);
+        let operations: Vec<Vec<usize>> = chunks
+            .iter()
+            .map(|chunk| chunk.iter().map(|step| step.operation).collect())
+            .collect();
+        assert_eq!(operations, vec![vec![0, 1], vec![2, 3]]);
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::{json, Value};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{reserved::ReservedAliases, store::memory::InMemoryStore};
+
+    fn app_state() -> AppState {
+        let rustlinks = Arc::new(RwLock::new(RustlinkIndex::default()));
+
+        AppState {
+            rustlinks: rustlinks.clone(),
+            store: Arc::new(InMemoryStore::new(rustlinks, vec![])),
+            reserved: ReservedAliases::new("/login", "/oauth/callback", &[]),
+            ..AppState::for_tests()
+        }
+    }
+
+    fn put(alias: &str) -> Value {
+        json!({
+            "op": "put",
+            "alias": alias,
+            "link": { "url": format!("https://example.com/{}", alias) },
+        })
+    }
+
+    #[actix_web::test]
+    async fn it_applies_batches_and_reports_their_changes() {
+        let data = web::Data::new(app_state());
+        let app =
+            test::init_service(App::new().app_data(data.clone()).service(batch_rustlinks)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/links:batch")
+            .set_json(json!({ "operations": [put("standup"), put("retro")] }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["applied"], 2);
+        assert_eq!(resp["revision"], 1);
+
+        let operations = json!([
+            { "op": "rename", "from": "standup", "to": "team/standup" },
+            put("standup"),
+            { "op": "delete", "alias": "retro" },
+        ]);
+        let req = test::TestRequest::post()
+            .uri("/links:batch")
+            .set_json(json!({ "operations": operations, "dry_run": true }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["applied"], 0);
+        let changes: Vec<(&str, bool, bool)> = resp["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| {
+                (
+                    change["alias"].as_str().unwrap(),
+                    change["before"].is_null(),
+                    change["after"].is_null(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("standup", false, false),
+                ("team/standup", true, false),
+                ("retro", false, true),
+            ]
+        );
+        assert_eq!(data.rustlinks.read().await.len(), 2);
+
+        let req = test::TestRequest::post()
+            .uri("/links:batch")
+            .set_json(json!({ "operations": operations }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["applied"], 3);
+        let rustlinks = data.rustlinks.read().await;
+        assert_eq!(rustlinks["team/standup"].url, "https://example.com/standup");
+        assert!(rustlinks.contains_key("standup"));
+        assert!(!rustlinks.contains_key("retro"));
+    }
+
+    #[actix_web::test]
+    async fn it_rejects_batches_with_invalid_operations() {
+        let data = web::Data::new(app_state());
+        let app =
+            test::init_service(App::new().app_data(data.clone()).service(batch_rustlinks)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/links:batch")
+            .set_json(json!({
+                "operations": [
+                    put("standup"),
+                    put("api/docs"),
+                    { "op": "delete", "alias": "retro" },
+                    { "op": "put", "alias": "oncall", "link": {} },
+                ],
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp: Value = test::read_body_json(resp).await;
+        let failed: Vec<u64> = resp["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["operation"].as_u64().unwrap())
+            .collect();
+        assert_eq!(failed, vec![1, 2, 3]);
+        assert!(data.rustlinks.read().await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn it_commits_large_batches_in_chunks() {
+        let data = web::Data::new(app_state());
+        let app =
+            test::init_service(App::new().app_data(data.clone()).service(batch_rustlinks)).await;
+        let operations: Vec<Value> = (0..MAX_TXN_OPS + 2)
+            .map(|i| put(&format!("link{}", i)))
+            .chain([json!({ "op": "delete", "alias": "link0" })])
+            .collect();
+
+        let req = test::TestRequest::post()
+            .uri("/links:batch")
+            .set_json(json!({ "operations": operations }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["applied"], MAX_TXN_OPS + 3);
+        assert_eq!(resp["revision"], 2);
+        // Created and deleted within the batch, so it's no change at all
+        assert_eq!(resp["changes"].as_array().unwrap().len(), MAX_TXN_OPS + 1);
+        assert_eq!(data.rustlinks.read().await.len(), MAX_TXN_OPS + 1);
+    }
+}
@@ -0,0 +1,278 @@
+use actix_web::{delete, get, put, web, HttpResponse, Responder};
+use serde::Serialize;
+
+use super::links::RustlinkView;
+use crate::{
+    collection::Collection,
+    index::RustlinkIndex,
+    state::AppState,
+    store::{Condition, Operation},
+    util::{self, AliasNormalization},
+};
+
+/// A collection as returned by the API, along with the links it lists
+#[derive(Serialize)]
+pub struct CollectionView<'a> {
+    pub name: &'a str,
+    #[serde(flatten)]
+    pub collection: &'a Collection,
+    /// The links in the collection, in order
+    pub links: Vec<RustlinkView<'a>>,
+    /// Aliases in the collection which don't (or no longer) have a link
+    pub missing: Vec<&'a str>,
+}
+
+impl<'a> CollectionView<'a> {
+    /// View `collection`, stored under the canonical `name`, with its aliases
+    /// resolved against `rustlinks`
+    pub fn new(
+        name: &'a str,
+        collection: &'a Collection,
+        rustlinks: &'a RustlinkIndex,
+        normalizations: &[AliasNormalization],
+    ) -> Self {
+        let mut links: Vec<RustlinkView> = vec![];
+        let mut missing: Vec<&str> = vec![];
+
+        for alias in &collection.aliases {
+            match rustlinks.get_key_value(&util::canonicalize_alias(alias, normalizations)) {
+                Some((canonical, rustlink)) => links.push(RustlinkView::new(canonical, rustlink)),
+                None => missing.push(alias),
+            }
+        }
+
+        CollectionView {
+            name: collection.display_name.as_deref().unwrap_or(name),
+            collection,
+            links,
+            missing,
+        }
+    }
+}
+
+fn not_found(name: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!("No collection exists named `{}`", name))
+}
+
+/// Every collection, in name order
+#[get("")]
+pub async fn get_collections(data: web::Data<AppState>) -> impl Responder {
+    let collections = data.collections.read().await;
+    let rustlinks = data.rustlinks.read().await;
+    let mut views: Vec<CollectionView> = collections
+        .iter()
+        .map(|(name, collection)| {
+            CollectionView::new(name, collection, &rustlinks, &data.alias_normalization)
+        })
+        .collect();
+    views.sort_by(|a, b| a.name.cmp(b.name));
+
+    HttpResponse::Ok().json(views)
+}
+
+/// The collection named `name`, read from the store rather than the synced
+/// collections so that writes are seen immediately
+#[get("/{name}")]
+pub async fn get_collection(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let name = path.into_inner();
+    let key = util::collection_to_key(&name, &data.alias_normalization);
+
+    let stored = match data.store.get(&key).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return not_found(&name),
+        Err(e) => {
+            eprintln!("Failed to GET from etcd: {:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    let mut collection = match serde_json::from_slice::<Collection>(&stored.value) {
+        Ok(collection) => collection,
+        Err(e) => {
+            eprintln!("Failed to parse collection stored at {}: {:?}", key, e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    collection.revision = Some(stored.mod_revision);
+    let rustlinks = data.rustlinks.read().await;
+
+    HttpResponse::Ok().json(CollectionView::new(
+        &name,
+        &collection,
+        &rustlinks,
+        &data.alias_normalization,
+    ))
+}
+
+/// Create or replace the collection named `name`. Its aliases don't need to
+/// have links (yet), they're listed as missing until they do.
+#[put("/{name}")]
+pub async fn put_collection(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    collection: web::Json<Collection>,
+) -> impl Responder {
+    let name = path.into_inner();
+    let mut collection = collection.into_inner();
+
+    if collection.title.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Collections must have a title");
+    }
+    collection.display_name = Some(name.clone());
+    collection.revision = None;
+
+    let bytes = match serde_json::to_vec(&collection) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to serialize collection: {:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    let key = util::collection_to_key(&name, &data.alias_normalization);
+
+    match data.store.put(key, bytes).await {
+        Ok(revision) => {
+            collection.revision = Some(revision);
+            let rustlinks = data.rustlinks.read().await;
+
+            HttpResponse::Ok().json(CollectionView::new(
+                &name,
+                &collection,
+                &rustlinks,
+                &data.alias_normalization,
+            ))
+        }
+        Err(e) => {
+            eprintln!("Failed to PUT to etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+/// Delete the collection named `name`, leaving its links as they are
+#[delete("/{name}")]
+pub async fn delete_collection(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    let key = util::collection_to_key(&name, &data.alias_normalization);
+    let deleted = data
+        .store
+        .txn(
+            vec![Condition::Present(key.clone())],
+            vec![Operation::Delete(key)],
+        )
+        .await;
+
+    match deleted {
+        Ok(Some(_)) => HttpResponse::Ok().body("OK"),
+        Ok(None) => not_found(&name),
+        Err(e) => {
+            eprintln!("Failed to DELETE from etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::{json, Value};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{rustlink::Rustlink, RustlinkAlias};
+
+    #[actix_web::test]
+    async fn it_stores_collections_and_resolves_their_links() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+
+        for alias in ["payments/runbook", "oncall"] {
+            rustlinks.insert(
+                alias.to_string(),
+                Rustlink {
+                    url: format!("https://example.com/{}", alias),
+                    ..Default::default()
+                },
+            );
+        }
+        let collections = HashMap::from([(
+            "infra".to_string(),
+            Collection {
+                title: "Infrastructure".to_string(),
+                aliases: vec!["oncall".to_string()],
+                ..Default::default()
+            },
+        )]);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    alias_normalization: vec![AliasNormalization::Case],
+                    collections: Arc::new(RwLock::new(collections)),
+                    ..AppState::for_tests()
+                }))
+                .service(
+                    web::scope("/collections")
+                        .service(get_collections)
+                        .service(get_collection)
+                        .service(put_collection)
+                        .service(delete_collection),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/collections/Payments")
+            .set_json(json!({
+                "title": "Payments team",
+                "aliases": ["Payments/Runbook", "oncall", "retro"],
+            }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["name"], "Payments");
+        assert_eq!(resp["revision"], 1);
+
+        let req = test::TestRequest::get()
+            .uri("/collections/payments")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["title"], "Payments team");
+        let aliases: Vec<&str> = resp["links"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|link| link["alias"].as_str().unwrap())
+            .collect();
+        assert_eq!(aliases, vec!["payments/runbook", "oncall"]);
+        assert_eq!(resp["missing"], json!(["retro"]));
+
+        let req = test::TestRequest::put()
+            .uri("/collections/payments")
+            .set_json(json!({ "title": " ", "aliases": [] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // Listed from the synced collections
+        let req = test::TestRequest::get().uri("/collections").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp[0]["name"], "infra");
+        assert_eq!(resp[0]["links"][0]["url"], "https://example.com/oncall");
+
+        let req = test::TestRequest::delete()
+            .uri("/collections/PAYMENTS")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .uri("/collections/payments")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
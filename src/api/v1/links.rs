@@ -1,47 +1,1352 @@
-use actix_web::{delete, get, put, web, HttpResponse, Responder};
-use etcd_rs::{KeyValueOp, PutRequest};
+use actix_web::{delete, get, http::StatusCode, patch, post, put, web, HttpResponse, Responder};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-use crate::{rustlink::Rustlink, state::AppState, util};
+use super::batch::{self, BatchOperation, BatchResult};
+use crate::{
+    index::{overlay::Overlay, Links, RustlinkIndex},
+    oidc::identity::Identity,
+    rustlink::{normalize_tag, RedirectMode, Rustlink, REFERENCE_PREFIX},
+    shortcode,
+    state::AppState,
+    store::{Condition, Operation},
+    template,
+    trash::TrashedRustlink,
+    util, RustlinkAlias,
+};
+
+/// Links listed per page unless a `limit` is given
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// A link as returned by the API, along with its alias and which of its
+/// targets is currently in effect
+#[derive(Serialize)]
+pub struct RustlinkView<'a> {
+    pub alias: &'a str,
+    #[serde(flatten)]
+    pub rustlink: &'a Rustlink,
+    /// Index into `schedule` of the active target, if any (otherwise `url` is
+    /// in effect)
+    pub active_target: Option<usize>,
+    pub active_url: &'a str,
+}
+
+impl<'a> RustlinkView<'a> {
+    /// View `rustlink`, stored under the canonical `alias`
+    pub fn new(alias: &'a str, rustlink: &'a Rustlink) -> Self {
+        let now = Utc::now();
+
+        RustlinkView {
+            alias: rustlink.display_alias.as_deref().unwrap_or(alias),
+            rustlink,
+            active_target: rustlink.active_target(now),
+            active_url: rustlink.active_url(now),
+        }
+    }
+}
+
+/// The order links are listed in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    #[default]
+    Alias,
+    /// Most recently modified first
+    Modified,
+    /// Most redirected to (by this node) first. Pages continue from the usage
+    /// the previous page's last link had when it was listed, so links whose
+    /// usage changes in between pages may be skipped or listed twice.
+    Popularity,
+}
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    limit: Option<usize>,
+    /// Continue listing after the page which returned this cursor
+    cursor: Option<String>,
+    /// Only list links whose alias starts with this
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    sort: Sort,
+}
+
+/// The position of the last link of a page in its sort order, which the next
+/// page starts after. Positioning by alias (rather than by offset) keeps pages
+/// stable while links are created and deleted in between requests.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    sort: Sort,
+    alias: RustlinkAlias,
+    /// The revision or usage of the link, for sorts other than by alias
+    #[serde(default)]
+    key: u64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Option<Cursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// A page of links, along with the revision of the links it was listed from
+#[derive(Serialize)]
+pub struct RustlinkPage<'a> {
+    pub links: Vec<RustlinkView<'a>>,
+    /// Pass as `cursor` to list the next page, absent on the last page
+    pub next_cursor: Option<String>,
+    pub revision: i64,
+}
 
 #[get("/")]
-pub async fn get_rustlinks(data: web::Data<AppState>) -> impl Responder {
-    // TODO: cursor-based pagination
-    // TODO: search queries
+pub async fn get_rustlinks(
+    data: web::Data<AppState>,
+    query: web::Query<ListQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let after = match query.cursor.as_deref().map(Cursor::decode) {
+        Some(Some(cursor)) if cursor.sort != query.sort => {
+            return HttpResponse::BadRequest().body("Cursor was returned for a different sort");
+        }
+        Some(None) => return HttpResponse::BadRequest().body("Invalid cursor"),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let prefix = util::canonicalize_alias(&query.prefix, &data.alias_normalization);
     let rustlinks = data.rustlinks.read().await;
-    return HttpResponse::Ok().json(rustlinks.values().collect::<Vec<&Rustlink>>());
+    let revision = *data.revision.read().await;
+
+    // Take one more link than the page holds, to tell whether there's a next page
+    let mut page: Vec<(&RustlinkAlias, &Rustlink, u64)> = match query.sort {
+        Sort::Alias => rustlinks
+            .range(&prefix, after.as_ref().map(|cursor| cursor.alias.as_str()))
+            .take(limit + 1)
+            .map(|(alias, rustlink)| (alias, rustlink, 0))
+            .collect(),
+        Sort::Modified => rustlinks
+            .recently_modified(
+                &prefix,
+                after
+                    .as_ref()
+                    .map(|cursor| (cursor.key as i64, cursor.alias.as_str())),
+            )
+            .take(limit + 1)
+            .map(|(alias, rustlink)| {
+                (
+                    alias,
+                    rustlink,
+                    rustlink.revision.unwrap_or_default() as u64,
+                )
+            })
+            .collect(),
+        Sort::Popularity => rustlinks.popular(
+            &prefix,
+            &data.usage,
+            after
+                .as_ref()
+                .map(|cursor| (cursor.key, cursor.alias.as_str())),
+            limit + 1,
+        ),
+    };
+    let mut next_cursor = None;
+
+    if page.len() > limit {
+        page.truncate(limit);
+        next_cursor = page.last().map(|(alias, _, key)| {
+            Cursor {
+                sort: query.sort,
+                alias: alias.to_string(),
+                key: *key,
+            }
+            .encode()
+        });
+    }
+    HttpResponse::Ok().json(RustlinkPage {
+        links: page
+            .into_iter()
+            .map(|(alias, rustlink, _)| RustlinkView::new(alias, rustlink))
+            .collect(),
+        next_cursor,
+        revision,
+    })
+}
+
+#[get("/{alias:.+}")]
+pub async fn get_rustlink(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let alias = path.into_inner();
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+
+    match fetch_rustlink(&data, &key).await {
+        Ok(Some((_, rustlink))) => HttpResponse::Ok().json(RustlinkView::new(&alias, &rustlink)),
+        Ok(None) => not_found(&alias),
+        Err(response) => response,
+    }
 }
 
-#[put("/{alias}")]
+/// The link stored at `key` as it was last modified, read from the store
+/// rather than the synced links so that writes are seen immediately
+async fn fetch_rustlink(
+    data: &AppState,
+    key: &str,
+) -> Result<Option<(serde_json::Value, Rustlink)>, HttpResponse> {
+    let stored = match data.store.get(key).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            eprintln!("Failed to GET from etcd: {:?}", e);
+            return Err(HttpResponse::InternalServerError().body("Internal Server Error"));
+        }
+    };
+    let parsed = serde_json::from_slice::<serde_json::Value>(&stored.value).and_then(|value| {
+        let mut rustlink = serde_json::from_value::<Rustlink>(value.clone())?;
+        rustlink.revision = Some(stored.mod_revision);
+        Ok((value, rustlink))
+    });
+
+    match parsed {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(e) => {
+            eprintln!("Failed to parse link stored at {}: {:?}", key, e);
+            Err(HttpResponse::InternalServerError().body("Internal Server Error"))
+        }
+    }
+}
+
+fn not_found(alias: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!("No link exists for alias `{}`", alias))
+}
+
+#[derive(Deserialize)]
+pub struct CreateQuery {
+    /// Replace an existing link whose alias shares the same canonical form,
+    /// but is spelled differently
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[put("/{alias:.+}")]
 pub async fn create_rustlink(
     data: web::Data<AppState>,
+    identity: Option<Identity>,
     path: web::Path<String>,
+    query: web::Query<CreateQuery>,
     rustlink: web::Json<Rustlink>,
 ) -> impl Responder {
     println!("creating rust link");
-    if let Ok(bytes) = serde_json::to_vec(&rustlink) {
-        let key = util::alias_to_key(&path.into_inner());
-        println!("using key: {:?} and value: {:?}", key, rustlink);
-        let req = PutRequest::new(key, bytes.to_owned());
-        match data.etcd_client.put(req).await {
-            Ok(_) => HttpResponse::Ok().body("OK"),
+    let alias = path.into_inner();
+
+    if let Some(reserved) = data.reserved.check(&alias) {
+        return HttpResponse::BadRequest().body(format!(
+            "Alias `{}` is reserved for {}",
+            alias, reserved.reason
+        ));
+    }
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+    let prepared = check_rustlink(
+        &data,
+        &*data.rustlinks.read().await,
+        &alias,
+        query.overwrite,
+        rustlink.into_inner(),
+        identity.as_ref(),
+    );
+
+    match prepared {
+        Ok((rustlink, bytes)) => store_rustlink(&data, key, &alias, rustlink, bytes).await,
+        Err(response) => response,
+    }
+}
+
+/// Validate `rustlink` against the links it'll live alongside, returning it
+/// along with the value to store
+fn check_rustlink(
+    data: &AppState,
+    rustlinks: &RustlinkIndex,
+    alias: &str,
+    overwrite: bool,
+    rustlink: Rustlink,
+    identity: Option<&Identity>,
+) -> Result<(Rustlink, Vec<u8>), HttpResponse> {
+    let canonical = util::canonicalize_alias(alias, &data.alias_normalization);
+
+    if !overwrite && let Some(collision) = collision(data, rustlinks, alias) {
+        return Err(HttpResponse::Conflict().body(collision));
+    }
+    prepare_rustlink(
+        data,
+        rustlinks,
+        alias,
+        rustlink,
+        rustlinks.get(&canonical),
+        identity,
+    )
+}
+
+/// Why `alias` can't be used without overwriting an existing link, if it
+/// shares its canonical form with an existing alias spelled differently
+pub(super) fn collision(data: &AppState, rustlinks: &impl Links, alias: &str) -> Option<String> {
+    let canonical = util::canonicalize_alias(alias, &data.alias_normalization);
+    let existing = rustlinks.link(&canonical)?;
+    let existing_alias = existing.display_alias.as_deref().unwrap_or(&canonical);
+
+    (existing_alias != alias).then(|| {
+        format!(
+            "Alias `{}` collides with existing alias `{}`",
+            alias, existing_alias
+        )
+    })
+}
+
+/// Store a validated `rustlink` at `key`, responding with it as stored
+async fn store_rustlink(
+    data: &AppState,
+    key: String,
+    alias: &str,
+    mut rustlink: Rustlink,
+    bytes: Vec<u8>,
+) -> HttpResponse {
+    match data.store.put(key, bytes).await {
+        Ok(revision) => {
+            rustlink.revision = Some(revision);
+            HttpResponse::Ok().json(RustlinkView::new(alias, &rustlink))
+        }
+        Err(e) => {
+            eprintln!("Failed to PUT to etcd: {:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    }
+}
+
+/// Update a link by applying a JSON merge patch (RFC 7396) to it, e.g.
+/// `{"description": "...", "schedule": null}`
+#[patch("/{alias:.+}")]
+pub async fn update_rustlink(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    path: web::Path<String>,
+    patch: web::Json<serde_json::Value>,
+) -> impl Responder {
+    let alias = path.into_inner();
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+
+    let (mut value, existing) = match fetch_rustlink(&data, &key).await {
+        Ok(Some(fetched)) => fetched,
+        Ok(None) => return not_found(&alias),
+        Err(response) => return response,
+    };
+    merge_patch(&mut value, patch.into_inner());
+
+    let rustlink = match serde_json::from_value::<Rustlink>(value) {
+        Ok(rustlink) => rustlink,
+        Err(e) => return HttpResponse::BadRequest().body(format!("Invalid link: {}", e)),
+    };
+    // Keep the alias as it was spelled when the link was created, rather
+    // than however it was spelled in this request
+    let display_alias = existing.display_alias.clone().unwrap_or(alias);
+    let prepared = prepare_rustlink(
+        &data,
+        &*data.rustlinks.read().await,
+        &display_alias,
+        rustlink,
+        Some(&existing),
+        identity.as_ref(),
+    );
+    let (mut rustlink, bytes) = match prepared {
+        Ok(prepared) => prepared,
+        Err(response) => return response,
+    };
+    // Only apply the patch to the version of the link it was merged with
+    let condition = Condition::ModRevision(key.clone(), existing.revision.unwrap_or_default());
+
+    match data
+        .store
+        .txn(vec![condition], vec![Operation::Put(key, bytes)])
+        .await
+    {
+        Ok(Some(revision)) => {
+            rustlink.revision = Some(revision);
+            HttpResponse::Ok().json(RustlinkView::new(&display_alias, &rustlink))
+        }
+        Ok(None) => HttpResponse::Conflict().body(format!(
+            "Link `{}` was modified concurrently, retry the update",
+            display_alias
+        )),
+        Err(e) => {
+            eprintln!("Failed to PATCH in etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+/// Apply a JSON merge patch to `target`, where `null` removes a field
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (field, value) in patch {
+        if value.is_null() {
+            target.remove(&field);
+        } else {
+            merge_patch(
+                target.entry(field).or_insert(serde_json::Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+/// Record when `rustlink` is being stored and by whom, carrying over when
+/// (and by whom) the `existing` link was created. Links created without an
+/// owner are owned by their creator.
+fn stamp(
+    rustlink: &mut Rustlink,
+    existing: Option<&Rustlink>,
+    identity: Option<&Identity>,
+    now: DateTime<Utc>,
+) {
+    let by = identity.map(Identity::owner);
+
+    match existing {
+        Some(existing) => {
+            rustlink.created_at = existing.created_at;
+            rustlink.created_by = existing.created_by.clone();
+        }
+        None => {
+            rustlink.created_at = Some(now);
+            rustlink.created_by = by.clone();
+
+            if rustlink.owners.is_empty() {
+                rustlink.owners.extend(by.clone());
+            }
+        }
+    }
+    rustlink.updated_at = Some(now);
+    rustlink.updated_by = by;
+}
+
+/// Validate `rustlink`, to be stored at `alias` in place of `existing` (if
+/// any) by `identity`, returning it along with the value to store
+fn prepare_rustlink(
+    data: &AppState,
+    rustlinks: &RustlinkIndex,
+    alias: &str,
+    rustlink: Rustlink,
+    existing: Option<&Rustlink>,
+    identity: Option<&Identity>,
+) -> Result<(Rustlink, Vec<u8>), HttpResponse> {
+    validate_rustlink(data, rustlinks, alias, rustlink, existing, identity)
+        .map_err(|e| HttpResponse::BadRequest().body(e))
+}
+
+/// Like [`prepare_rustlink`], but describing why `rustlink` is invalid rather
+/// than responding with it
+pub(super) fn validate_rustlink(
+    data: &AppState,
+    rustlinks: &impl Links,
+    alias: &str,
+    mut rustlink: Rustlink,
+    existing: Option<&Rustlink>,
+    identity: Option<&Identity>,
+) -> Result<(Rustlink, Vec<u8>), String> {
+    let canonical = util::canonicalize_alias(alias, &data.alias_normalization);
+    rustlink.display_alias = Some(alias.to_string());
+    rustlink.revision = None;
+    stamp(&mut rustlink, existing, identity, Utc::now());
+
+    let mut tags: Vec<String> = vec![];
+    for tag in rustlink.tags.drain(..) {
+        let tag = normalize_tag(&tag);
+
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    rustlink.tags = tags;
+
+    if rustlink.url.is_empty() && rustlink.bundle.is_empty() {
+        return Err("Links need either a `url` or a `bundle` of URLs".to_string());
+    }
+
+    // Targets are picked per request and bundles aren't redirected to, so
+    // neither is resolved as a reference
+    if rustlink
+        .targets
+        .iter()
+        .map(|target| target.url.as_str())
+        .chain(rustlink.bundle.iter().map(String::as_str))
+        .any(|url| url.starts_with(REFERENCE_PREFIX))
+    {
+        return Err(format!(
+            "Only a link's `url` and `schedule` can refer to other aliases (`{}<alias>`)",
+            REFERENCE_PREFIX
+        ));
+    }
+    // References are followed as the link they end at is configured, so a
+    // link referring to another can't configure how it's followed itself
+    if rustlink.references().next().is_some()
+        && (rustlink.append_path
+            || rustlink.forward_query
+            || rustlink.redirect != RedirectMode::default()
+            || !rustlink.targets.is_empty()
+            || !rustlink.bundle.is_empty())
+    {
+        return Err(
+            "Links referring to other aliases are followed as those are configured, so they \
+             can't set `append_path`, `forward_query`, `redirect`, `targets` or `bundle`"
+                .to_string(),
+        );
+    }
+    rustlink
+        .urls()
+        .try_for_each(|url| {
+            template::validate(url, &data.template_variables)?;
+
+            match url.starts_with(REFERENCE_PREFIX) {
+                true => Ok(()),
+                false => template::validate_scheme(url, &data.template_variables),
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+    rustlinks
+        .check_references(&canonical, &rustlink, &data.alias_normalization)
+        .map_err(|e| e.to_string())?;
+    match serde_json::to_vec(&rustlink) {
+        Ok(bytes) => Ok((rustlink, bytes)),
+        Err(_) => Err(format!("Failed to parse JSON: {:?}", rustlink)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    to: String,
+    /// Leave a deprecated link at the old alias, which keeps redirecting to
+    /// the new one
+    #[serde(default)]
+    leave_pointer: bool,
+}
+
+/// Move the link at `alias` to a new alias in a single transaction, keeping
+/// when and by whom it was created
+#[post("/{alias:.+}/rename")]
+pub async fn rename_rustlink(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    path: web::Path<String>,
+    rename: web::Json<RenameRequest>,
+) -> impl Responder {
+    let alias = path.into_inner();
+    let RenameRequest { to, leave_pointer } = rename.into_inner();
+    let canonical = util::canonicalize_alias(&alias, &data.alias_normalization);
+    let to_canonical = util::canonicalize_alias(&to, &data.alias_normalization);
+
+    // Both aliases are read from the store rather than the synced links, so
+    // that renames right after other writes see them. The transaction only
+    // commits if neither has changed since.
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+    let to_key = util::alias_to_key(&to, &data.alias_normalization);
+
+    let existing = match fetch_rustlink(&data, &key).await {
+        Ok(Some((_, existing))) => existing,
+        Ok(None) => return not_found(&alias),
+        Err(response) => return response,
+    };
+    if to_canonical != canonical {
+        match fetch_rustlink(&data, &to_key).await {
+            Ok(Some(_)) => {
+                return HttpResponse::Conflict().body(format!("Alias `{}` already has a link", to));
+            }
+            Ok(None) => {}
+            Err(response) => return response,
+        }
+    }
+    let planned = {
+        let rustlinks = data.rustlinks.read().await;
+        let mut simulated = Overlay::new(&rustlinks);
+
+        simulated.insert(canonical.clone(), existing);
+        if to_canonical != canonical {
+            simulated.remove(&to_canonical);
+        }
+        batch::plan_over(
+            &data,
+            simulated,
+            vec![BatchOperation::Rename {
+                from: alias,
+                to: to.clone(),
+                leave_pointer,
+            }],
+            identity.as_ref(),
+        )
+    };
+    let plan = match planned {
+        Ok(plan) => plan,
+        Err(errors) => {
+            let errors: Vec<String> = errors.into_iter().map(|e| e.error).collect();
+            return HttpResponse::BadRequest().body(errors.join("\n"));
+        }
+    };
+    let Some(mut renamed) = plan
+        .changes
+        .iter()
+        .find(|change| change.alias == to)
+        .and_then(|change| change.after.clone())
+    else {
+        return HttpResponse::InternalServerError().body("Internal Server Error");
+    };
+    let mut result = BatchResult {
+        dry_run: false,
+        changes: plan.changes,
+        applied: 0,
+        revision: None,
+        errors: vec![],
+        failed: None,
+    };
+
+    // The link keeps its usage. It's moved ahead of the commit, so that it's
+    // not forgotten along with the old alias once its deletion is watched.
+    data.usage.rename(&canonical, &to_canonical);
+
+    // Renames make at most two writes, so they're committed in one chunk
+    match batch::commit(&data, plan.steps, plan.expected, &mut result).await {
+        StatusCode::OK => {
+            renamed.revision = result.revision;
+            HttpResponse::Ok().json(RustlinkView::new(&to_canonical, &renamed))
+        }
+        status => {
+            data.usage.rename(&to_canonical, &canonical);
+            HttpResponse::build(status).body(
+                result
+                    .failed
+                    .map(|failure| failure.error)
+                    .unwrap_or_default(),
+            )
+        }
+    }
+}
+
+/// Create a link under a newly minted short code, responding with the code
+#[post("")]
+pub async fn create_short_rustlink(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    rustlink: web::Json<Rustlink>,
+) -> impl Responder {
+    let rustlink = rustlink.into_inner();
+
+    for attempt in 0..shortcode::MAX_ATTEMPTS {
+        let code = data.short_codes.generate(attempt);
+
+        if data.reserved.check(&code).is_some() {
+            continue;
+        }
+        let prepared = prepare_rustlink(
+            &data,
+            &*data.rustlinks.read().await,
+            &code,
+            rustlink.clone(),
+            None,
+            identity.as_ref(),
+        );
+        let bytes = match prepared {
+            Ok((_, bytes)) => bytes,
+            Err(response) => return response,
+        };
+        let key = util::alias_to_key(&code, &data.alias_normalization);
+        // Only create the key if it doesn't exist yet, so concurrent requests
+        // can't claim the same code
+        let created = data
+            .store
+            .txn(
+                vec![Condition::Absent(key.clone())],
+                vec![Operation::Put(key, bytes)],
+            )
+            .await;
+
+        match created {
+            Ok(Some(_)) => {
+                return HttpResponse::Created().json(json!({ "alias": code }));
+            }
+            Ok(None) => continue,
             Err(e) => {
-                eprintln!("Failed to PUT to etcd: {:?}", e);
+                eprintln!("Failed to create short code in etcd: {:?}", e);
                 return HttpResponse::InternalServerError().body("Internal Server Error");
             }
         }
-    } else {
-        HttpResponse::BadRequest().body(format!("Failed to parse JSON: {:?}", rustlink))
+    }
+    HttpResponse::ServiceUnavailable().body("Failed to find an unused short code")
+}
+
+/// List the personal links of the requesting user
+#[get("/~")]
+pub async fn get_personal_rustlinks(
+    data: web::Data<AppState>,
+    identity: Identity,
+) -> impl Responder {
+    match data.personal_rustlinks(&identity.owner()).await {
+        Ok(rustlinks) => HttpResponse::Ok().json(
+            rustlinks
+                .iter()
+                .map(|(alias, rustlink)| RustlinkView::new(alias, rustlink))
+                .collect::<Vec<RustlinkView>>(),
+        ),
+        Err(e) => {
+            eprintln!("Failed to GET personal links from etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
     }
 }
 
-#[delete("/{alias}")]
-pub async fn delete_rustlink(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+/// Create a personal link, resolved by `go/~/{alias}` for the requesting user
+#[put("/~/{alias:.+}")]
+pub async fn create_personal_rustlink(
+    data: web::Data<AppState>,
+    identity: Identity,
+    path: web::Path<String>,
+    query: web::Query<CreateQuery>,
+    rustlink: web::Json<Rustlink>,
+) -> impl Responder {
     let alias = path.into_inner();
-    match data.etcd_client.delete(alias).await {
-        Ok(_) => return HttpResponse::Ok().body("OK"),
+    let owner = identity.owner();
+    let key = util::personal_alias_to_key(&owner, &alias, &data.alias_normalization);
+
+    match data.personal_rustlinks(&owner).await {
+        Ok(rustlinks) => {
+            match check_rustlink(
+                &data,
+                &rustlinks,
+                &alias,
+                query.overwrite,
+                rustlink.into_inner(),
+                Some(&identity),
+            ) {
+                Ok((rustlink, bytes)) => store_rustlink(&data, key, &alias, rustlink, bytes).await,
+                Err(response) => response,
+            }
+        }
         Err(e) => {
-            eprintln!("Failed to DELETE from etcd: {:?}", e);
+            eprintln!("Failed to GET personal links from etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[delete("/~/{alias:.+}")]
+pub async fn delete_personal_rustlink(
+    data: web::Data<AppState>,
+    identity: Identity,
+    path: web::Path<String>,
+) -> impl Responder {
+    let alias = path.into_inner();
+    let key = util::personal_alias_to_key(&identity.owner(), &alias, &data.alias_normalization);
+
+    remove_rustlink(&data, key, &alias).await
+}
+
+/// Move the link at `alias` into the trash, where it's kept until it's
+/// restored or purged
+#[delete("/{alias:.+}")]
+pub async fn delete_rustlink(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let alias = path.into_inner();
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+
+    let (revision, rustlink) = match fetch_rustlink(&data, &key).await {
+        Ok(Some((_, rustlink))) => (rustlink.revision.unwrap_or_default(), rustlink),
+        Ok(None) => return not_found(&alias),
+        Err(response) => return response,
+    };
+    let trashed = TrashedRustlink::new(rustlink, identity.as_ref(), Utc::now());
+    let bytes = match serde_json::to_vec(&trashed) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to serialize trashed link: {:?}", e);
             return HttpResponse::InternalServerError().body("Internal Server Error");
         }
+    };
+    let trash_key = util::alias_to_trash_key(&alias, &data.alias_normalization);
+    let deleted = data
+        .store
+        .txn(
+            vec![Condition::ModRevision(key.clone(), revision)],
+            vec![Operation::Delete(key), Operation::Put(trash_key, bytes)],
+        )
+        .await;
+
+    match deleted {
+        Ok(Some(_)) => HttpResponse::Ok().body("OK"),
+        Ok(None) => HttpResponse::Conflict().body(format!(
+            "The link for alias `{}` changed while it was being deleted",
+            alias
+        )),
+        Err(e) => {
+            eprintln!("Failed to DELETE from etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+/// Permanently delete the link stored at `key`, if there is one
+async fn remove_rustlink(data: &AppState, key: String, alias: &str) -> HttpResponse {
+    let deleted = data
+        .store
+        .txn(
+            vec![Condition::Present(key.clone())],
+            vec![Operation::Delete(key)],
+        )
+        .await;
+
+    match deleted {
+        Ok(Some(_)) => HttpResponse::Ok().body("OK"),
+        Ok(None) => not_found(alias),
+        Err(e) => {
+            eprintln!("Failed to DELETE from etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn it_stamps_creators_as_owners_and_keeps_who_created_links() {
+        let ada = Identity {
+            issuer: "https://accounts.example.com".to_string(),
+            subject: "1".to_string(),
+            username: None,
+            email: Some("ada@example.com".to_string()),
+            groups: vec![],
+        };
+        let created_at = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        let updated_at = Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap();
+
+        let mut created = Rustlink::default();
+        stamp(&mut created, None, Some(&ada), created_at);
+        assert_eq!(created.owners, vec!["ada@example.com"]);
+        assert_eq!(created.created_by.as_deref(), Some("ada@example.com"));
+        assert_eq!(created.updated_at, Some(created_at));
+
+        let mut updated = Rustlink {
+            owners: vec!["payments".to_string()],
+            created_by: Some("mallory".to_string()),
+            ..Default::default()
+        };
+        stamp(&mut updated, Some(&created), None, updated_at);
+        assert_eq!(updated.owners, vec!["payments"]);
+        assert_eq!(updated.created_at, Some(created_at));
+        assert_eq!(updated.created_by.as_deref(), Some("ada@example.com"));
+        assert_eq!(updated.updated_at, Some(updated_at));
+        assert_eq!(updated.updated_by, None);
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::Value;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{store::memory::InMemoryStore, usage::Usage, util::AliasNormalization};
+
+    fn app_state(alias_normalization: Vec<AliasNormalization>) -> AppState {
+        let rustlinks = Arc::new(RwLock::new(RustlinkIndex::default()));
+
+        AppState {
+            rustlinks: rustlinks.clone(),
+            store: Arc::new(InMemoryStore::new(rustlinks, alias_normalization.clone())),
+            alias_normalization,
+            ..AppState::for_tests()
+        }
+    }
+
+    macro_rules! links_app {
+        ($state:expr) => {
+            test::init_service(
+                App::new().app_data(web::Data::new($state)).service(
+                    web::scope("/links")
+                        .service(get_rustlinks)
+                        .service(get_rustlink)
+                        .service(create_rustlink)
+                        .service(update_rustlink)
+                        .service(rename_rustlink)
+                        .service(create_short_rustlink)
+                        .service(delete_rustlink),
+                ),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn it_gets_and_lists_links_with_their_alias() {
+        let app = links_app!(app_state(vec![]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/docs/api")
+            .set_json(json!({ "url": "https://docs.example.com/api" }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["alias"], "docs/api");
+        assert_eq!(resp["revision"], 1);
+
+        let req = test::TestRequest::get().uri("/links/docs/api").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["alias"], "docs/api");
+        assert_eq!(resp["url"], "https://docs.example.com/api");
+        assert_eq!(resp["active_url"], "https://docs.example.com/api");
+        assert_eq!(resp["revision"], 1);
+
+        let req = test::TestRequest::get().uri("/links/").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["links"].as_array().unwrap().len(), 1);
+        assert_eq!(resp["links"][0]["alias"], "docs/api");
+        assert_eq!(resp["links"][0]["revision"], 1);
+
+        let req = test::TestRequest::get().uri("/links/docs").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn it_patches_links() {
+        let app = links_app!(app_state(vec![]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/standup")
+            .set_json(json!({
+                "url": "https://meet.example.com/standup",
+                "description": "Daily standup",
+            }))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::patch()
+            .uri("/links/standup")
+            .set_json(json!({ "description": null, "owners": ["ada"] }))
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["url"], "https://meet.example.com/standup");
+        assert_eq!(resp["description"], Value::Null);
+        assert_eq!(resp["owners"], json!(["ada"]));
+        assert_eq!(resp["revision"], 2);
+
+        let req = test::TestRequest::patch()
+            .uri("/links/standup")
+            .set_json(json!({ "url": null }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::patch()
+            .uri("/links/retro")
+            .set_json(json!({ "owners": ["ada"] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn it_rejects_links_to_anything_but_web_pages() {
+        let app = links_app!(app_state(vec![]));
+
+        for link in [
+            json!({ "url": "javascript:alert(1)" }),
+            json!({ "url": "https://example.com", "schedule": [{ "url": "JavaScript:alert(1)" }] }),
+            json!({ "bundle": ["https://example.com", "data:text/html,hi"] }),
+        ] {
+            let req = test::TestRequest::put()
+                .uri("/links/xss")
+                .set_json(&link)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", link);
+        }
+    }
+
+    #[actix_web::test]
+    async fn it_rejects_references_from_targets_and_bundles() {
+        let app = links_app!(app_state(vec![]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/wiki")
+            .set_json(json!({ "url": "https://wiki.example.com" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        for link in [
+            json!({
+                "url": "https://example.com",
+                "targets": [{ "url": "alias:wiki", "rule": { "type": "weighted", "weight": 1 } }],
+            }),
+            json!({ "bundle": ["https://example.com", "alias:wiki"] }),
+        ] {
+            let req = test::TestRequest::put()
+                .uri("/links/docs")
+                .set_json(&link)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", link);
+        }
+    }
+
+    #[actix_web::test]
+    async fn it_rejects_settings_which_references_would_ignore() {
+        let app = links_app!(app_state(vec![]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/wiki")
+            .set_json(json!({ "url": "https://wiki.example.com" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        for link in [
+            json!({ "url": "alias:wiki", "append_path": true }),
+            json!({ "url": "alias:wiki", "forward_query": true }),
+            json!({ "url": "alias:wiki", "redirect": "moved_permanently" }),
+            json!({
+                "url": "https://example.com",
+                "schedule": [{ "url": "alias:wiki" }],
+                "targets": [{
+                    "url": "https://example.com/android",
+                    "rule": { "type": "header", "name": "User-Agent", "value": "Android" },
+                }],
+            }),
+        ] {
+            let req = test::TestRequest::put()
+                .uri("/links/docs")
+                .set_json(&link)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", link);
+            let body = test::read_body(resp).await;
+            assert!(
+                std::str::from_utf8(&body).unwrap().contains("can't set"),
+                "{}",
+                link
+            );
+        }
+
+        let req = test::TestRequest::put()
+            .uri("/links/docs")
+            .set_json(json!({ "url": "alias:wiki", "description": "Where docs live" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn it_stamps_links_with_when_and_by_whom_they_changed() {
+        let app = links_app!(app_state(vec![]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/standup")
+            .set_json(json!({
+                "url": "https://meet.example.com/standup",
+                "tags": [" Meetings", "team", "meetings", ""],
+                "created_by": "mallory",
+                "created_at": "2000-01-01T00:00:00Z",
+            }))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(created["tags"], json!(["meetings", "team"]));
+        assert_eq!(created["created_by"], Value::Null);
+        assert_ne!(created["created_at"], "2000-01-01T00:00:00Z");
+        assert_eq!(created["created_at"], created["updated_at"]);
+        assert_eq!(created["archived"], false);
+
+        let req = test::TestRequest::patch()
+            .uri("/links/standup")
+            .set_json(json!({ "archived": true }))
+            .to_request();
+        let updated: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(updated["archived"], true);
+        assert_eq!(updated["created_at"], created["created_at"]);
+        assert_ne!(updated["updated_at"], created["updated_at"]);
+    }
+
+    #[actix_web::test]
+    async fn it_renames_links_leaving_deprecated_pointers() {
+        let app = links_app!(app_state(vec![AliasNormalization::Case]));
+
+        for alias in ["standup", "retro"] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/links/{}", alias))
+                .set_json(json!({ "url": format!("https://meet.example.com/{}", alias) }))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        let req = test::TestRequest::get().uri("/links/standup").to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri("/links/standup/rename")
+            .set_json(json!({ "to": "Team/Standup", "leave_pointer": true }))
+            .to_request();
+        let renamed: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(renamed["alias"], "Team/Standup");
+        assert_eq!(renamed["url"], "https://meet.example.com/standup");
+        assert_eq!(renamed["created_at"], created["created_at"]);
+        assert_eq!(renamed["revision"], 3);
+
+        let req = test::TestRequest::get().uri("/links/standup").to_request();
+        let pointer: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pointer["url"], "alias:Team/Standup");
+        assert_eq!(pointer["deprecated"], true);
+        assert_eq!(pointer["revision"], 3);
+
+        let req = test::TestRequest::post()
+            .uri("/links/retro/rename")
+            .set_json(json!({ "to": "team/standup" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/links/retro/rename")
+            .set_json(json!({ "to": "team/retro" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/links/retro").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post()
+            .uri("/links/retro/rename")
+            .set_json(json!({ "to": "retrospective" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn it_renames_links_with_their_usage_without_leaving_references_dangling() {
+        let usage = Arc::new(Usage::from(HashMap::from([("standup".to_string(), 3)])));
+        let app = links_app!(AppState {
+            usage: usage.clone(),
+            ..app_state(vec![])
+        });
+
+        for (alias, url) in [
+            ("standup", "https://meet.example.com/standup"),
+            ("daily", "alias:standup"),
+        ] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/links/{}", alias))
+                .set_json(json!({ "url": url }))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/links/standup/rename")
+            .set_json(json!({ "to": "team/standup" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(resp).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains("`daily`"));
+        assert_eq!(usage.get("standup"), 3);
+
+        let req = test::TestRequest::post()
+            .uri("/links/standup/rename")
+            .set_json(json!({ "to": "team/standup", "leave_pointer": true }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert_eq!(usage.get("standup"), 0);
+        assert_eq!(usage.get("team/standup"), 3);
+    }
+
+    #[actix_web::test]
+    async fn it_renames_links_written_before_they_are_synced() {
+        // Writes to this store aren't synced to the links at all
+        let app = links_app!(AppState {
+            store: Arc::new(InMemoryStore::default()),
+            ..app_state(vec![])
+        });
+
+        for alias in ["standup", "retro"] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/links/{}", alias))
+                .set_json(json!({ "url": format!("https://meet.example.com/{}", alias) }))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/links/standup/rename")
+            .set_json(json!({ "to": "retro" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/links/standup/rename")
+            .set_json(json!({ "to": "team/standup" }))
+            .to_request();
+        let renamed: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(renamed["url"], "https://meet.example.com/standup");
+
+        let req = test::TestRequest::get().uri("/links/standup").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn it_deletes_links_by_their_canonical_key() {
+        let app = links_app!(app_state(vec![AliasNormalization::Case]));
+
+        let req = test::TestRequest::put()
+            .uri("/links/OnCall/Primary")
+            .set_json(json!({ "url": "https://pager.example.com" }))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/links/oncall/primary")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["alias"], "OnCall/Primary");
+
+        let req = test::TestRequest::delete()
+            .uri("/links/oncall/primary")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .uri("/links/OnCall/Primary")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/links/").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["links"], json!([]));
+
+        let req = test::TestRequest::delete()
+            .uri("/links/oncall/primary")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Follow cursors from `cursor` (or the first page) to the last page,
+    /// returning the aliases listed
+    macro_rules! list_all {
+        ($app:expr, $query:expr, $cursor:expr) => {{
+            let mut aliases: Vec<String> = vec![];
+            let mut cursor: Option<String> = $cursor;
+
+            loop {
+                let uri = match &cursor {
+                    Some(cursor) => format!("/links/?{}&cursor={}", $query, cursor),
+                    None => format!("/links/?{}", $query),
+                };
+                let req = test::TestRequest::get().uri(&uri).to_request();
+                let resp: Value = test::call_and_read_body_json($app, req).await;
+                assert!(resp["links"].as_array().unwrap().len() <= 2);
+                aliases.extend(
+                    resp["links"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|link| link["alias"].as_str().unwrap().to_string()),
+                );
+                match resp["next_cursor"].as_str() {
+                    Some(next) => cursor = Some(next.to_string()),
+                    None => break aliases,
+                }
+            }
+        }};
+    }
+
+    #[actix_web::test]
+    async fn it_pages_through_filtered_and_sorted_links() {
+        let state = app_state(vec![]);
+        let usage = state.usage.clone();
+        let app = links_app!(state);
+
+        for alias in ["payments/runbook", "standup", "payments", "oncall", "pay"] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/links/{}", alias))
+                .set_json(json!({ "url": "https://example.com" }))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        usage.extend([
+            ("standup".to_string(), 10),
+            ("pay".to_string(), 10),
+            ("oncall".to_string(), 3),
+        ]);
+
+        assert_eq!(
+            list_all!(&app, "limit=2", None),
+            vec!["oncall", "pay", "payments", "payments/runbook", "standup"]
+        );
+        assert_eq!(
+            list_all!(&app, "limit=2&prefix=pay", None),
+            vec!["pay", "payments", "payments/runbook"]
+        );
+        assert_eq!(
+            list_all!(&app, "limit=2&sort=modified", None),
+            vec!["pay", "oncall", "payments", "standup", "payments/runbook"]
+        );
+        assert_eq!(
+            list_all!(&app, "limit=2&sort=popularity", None),
+            vec!["pay", "standup", "oncall", "payments", "payments/runbook"]
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/links/?limit=1&prefix=pay")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["revision"], 0);
+        let cursor = resp["next_cursor"].as_str().unwrap().to_string();
+
+        // Links created after a page was listed don't shift the following pages
+        let req = test::TestRequest::put()
+            .uri("/links/pa")
+            .set_json(json!({ "url": "https://example.com" }))
+            .to_request();
+        test::call_service(&app, req).await;
+        assert_eq!(
+            list_all!(&app, "limit=2&prefix=pay", Some(cursor.clone())),
+            vec!["payments", "payments/runbook"]
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/links/?sort=modified&cursor={}", cursor))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 }
@@ -1,3 +1,10 @@
+pub mod batch;
+pub mod collections;
 pub mod health;
 pub mod links;
 pub mod oauth;
+pub mod opensearch;
+pub mod search;
+pub mod suggest;
+pub mod tags;
+pub mod trash;
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
 
 use actix_web::{
+    cookie::{Cookie, SameSite},
     get, post,
     web::{self, Redirect},
-    Either, HttpResponse, Responder,
+    Either, HttpRequest, HttpResponse, Responder,
 };
 use serde::Deserialize;
 
-use crate::state;
+use crate::{
+    oidc::identity::{bearer_token, verify, ID_TOKEN_COOKIE},
+    state,
+};
 
 #[derive(Deserialize)]
 struct LoginQuery {
@@ -50,7 +54,84 @@ pub async fn callback(query: web::Query<HashMap<String, String>>) -> impl Respon
 //     }
 // }
 
+/// Keep the ID token the UI signed in with in a cookie, so that following
+/// links (which can't send it as a bearer token) identifies the user too
+#[post("/session")]
+pub async fn session(req: HttpRequest, data: web::Data<state::AppState>) -> impl Responder {
+    let token = match bearer_token(&req) {
+        Some(token) if verify(token, &data).await.is_some() => token,
+        _ => return HttpResponse::Unauthorized().body("Unauthorized"),
+    };
+    let cookie = Cookie::build(ID_TOKEN_COOKIE, token.to_string())
+        .path("/")
+        .http_only(true)
+        .secure(req.connection_info().scheme() == "https")
+        .same_site(SameSite::Lax)
+        .finish();
+
+    HttpResponse::Ok().cookie(cookie).body("OK")
+}
+
 #[post("/logout")]
 pub async fn logout() -> impl Responder {
-    HttpResponse::Ok().body("OK")
+    let mut cookie = Cookie::build(ID_TOKEN_COOKIE, "").path("/").finish();
+    cookie.make_removal();
+
+    HttpResponse::Ok().cookie(cookie).body("OK")
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, test, App};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::oidc::provider::OIDCProvider;
+
+    #[actix_web::test]
+    async fn it_keeps_verified_id_tokens_in_a_cookie() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state::AppState {
+                    oidc_providers: Arc::new(RwLock::new(vec![OIDCProvider::for_tests()])),
+                    ..state::AppState::for_tests()
+                }))
+                .service(session)
+                .service(logout),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/session")
+            .insert_header(("Authorization", "Bearer not.a.token"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let token = OIDCProvider::id_token_for_tests("ada@example.com", &[]);
+        let req = test::TestRequest::post()
+            .uri("/session")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == ID_TOKEN_COOKIE)
+            .unwrap();
+        assert_eq!(cookie.value(), token);
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let req = test::TestRequest::post().uri("/logout").to_request();
+        let resp = test::call_service(&app, req).await;
+        let cookie = resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == ID_TOKEN_COOKIE)
+            .unwrap();
+        assert_eq!(cookie.value(), "");
+    }
 }
@@ -0,0 +1,236 @@
+use actix_web::{get, http::header::LOCATION, web, HttpResponse, Responder};
+use serde::Deserialize;
+use urlencoding::encode;
+
+use super::suggest::suggestions;
+use crate::{reserved::API_PATH, state::AppState};
+
+/// Where the OpenSearch endpoints are mounted, relative to the API
+pub const OPENSEARCH_PATH: &str = "/opensearch";
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The path of the link at `alias` (with any parameters), keeping the `/`s
+/// between its segments
+fn alias_path(alias: &str) -> String {
+    alias
+        .split('/')
+        .map(|segment| encode(segment).into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Describe rustlinks as a search engine, whose searches are resolved as
+/// aliases (with any parameters), e.g. `go standup` or `go jira 1234`
+pub fn description(public_url: &str) -> String {
+    let public_url = escape_xml(public_url);
+    let opensearch_url = format!("{}{}{}", public_url, API_PATH, OPENSEARCH_PATH);
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+  <ShortName>rustlinks</ShortName>
+  <Description>Go to a link by its alias</Description>
+  <InputEncoding>UTF-8</InputEncoding>
+  <Url type="text/html" method="get" template="{opensearch_url}/search?q={{searchTerms}}"/>
+  <Url type="application/x-suggestions+json" method="get" template="{opensearch_url}/suggest?q={{searchTerms}}"/>
+</OpenSearchDescription>
+"#
+    )
+}
+
+#[derive(Deserialize)]
+pub struct OpenSearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+#[get("/description.xml")]
+pub async fn opensearch_description(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/opensearchdescription+xml")
+        .body(description(&data.public_url))
+}
+
+/// Send a search submitted from the browser's address bar on to be resolved
+/// like any other request for a link, fallbacks included. Browsers encode
+/// search terms as a query parameter, so they're turned back into a path
+/// here, on whichever host the browser searched.
+#[get("/search")]
+pub async fn opensearch_search(query: web::Query<OpenSearchQuery>) -> impl Responder {
+    // Leading slashes would make the path another host's (e.g. `//evil.com`)
+    let alias = query.q.trim().trim_start_matches(['/', '\\']);
+
+    HttpResponse::Found()
+        .insert_header((LOCATION, format!("/{}", alias_path(alias))))
+        .finish()
+}
+
+/// Suggestions in the OpenSearch format browsers request as a search is
+/// typed: the query, followed by lists of completions, their descriptions,
+/// and their URLs
+#[get("/suggest")]
+pub async fn opensearch_suggest(
+    data: web::Data<AppState>,
+    query: web::Query<OpenSearchQuery>,
+) -> impl Responder {
+    let suggestions = suggestions(&data, query.q.trim(), None).await;
+    let completions: Vec<&str> = suggestions.iter().map(|s| s.alias.as_str()).collect();
+    let descriptions: Vec<&str> = suggestions
+        .iter()
+        .map(|s| s.description.as_deref().unwrap_or(&s.url))
+        .collect();
+    // Go through the redirect (rather than straight to the destination), so
+    // that templates are rendered and usage is counted
+    let urls: Vec<String> = suggestions
+        .iter()
+        .map(|s| format!("{}/{}", data.public_url, alias_path(&s.alias)))
+        .collect();
+
+    HttpResponse::Ok()
+        .content_type("application/x-suggestions+json")
+        .json((&query.q, completions, descriptions, urls))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_describes_search_and_suggestion_urls() {
+        let description = description("https://go");
+        assert!(description.contains(
+            r#"<Url type="text/html" method="get" template="https://go/api/v1/opensearch/search?q={searchTerms}"/>"#
+        ));
+        assert!(description
+            .contains(r#"template="https://go/api/v1/opensearch/suggest?q={searchTerms}"/>"#));
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{fallback::Fallback, redirect::redirect, rustlink::Rustlink, RustlinkAlias};
+
+    #[actix_web::test]
+    async fn it_serves_opensearch_searches_and_suggestions() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "payments/runbook".to_string(),
+            Rustlink {
+                url: "https://wiki.example.com/payments".to_string(),
+                description: Some("Payments runbook".to_string()),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "payroll".to_string(),
+            Rustlink {
+                url: "https://payroll.example.com".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    fallbacks: vec![Fallback::Template(
+                        "https://intranet.example.com/search?q={^}".to_string(),
+                    )],
+                    public_url: "https://go".to_string(),
+                    ..AppState::for_tests()
+                }))
+                .service(
+                    web::scope("/api/v1/opensearch")
+                        .service(opensearch_description)
+                        .service(opensearch_search)
+                        .service(opensearch_suggest),
+                )
+                .service(redirect),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/opensearch/description.xml")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/opensearchdescription+xml"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/opensearch/suggest?q=pay")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/x-suggestions+json"
+        );
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body,
+            json!([
+                "pay",
+                ["payments/runbook", "payroll"],
+                ["Payments runbook", "https://payroll.example.com"],
+                ["https://go/payments/runbook", "https://go/payroll"],
+            ])
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/opensearch/search?q=jira+1234")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "/jira%201234"
+        );
+
+        // Searches can't send browsers off to other hosts
+        for q in ["/evil.com", "//evil.com", "%5C%5Cevil.com"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/v1/opensearch/search?q={}", q))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.headers().get("location").unwrap().to_str().unwrap(),
+                "/evil.com",
+                "{}",
+                q
+            );
+        }
+
+        // Searches resolve through the redirect, falling back for unknown aliases
+        for (q, expected) in [
+            ("payroll", "https://payroll.example.com"),
+            ("wiki", "https://intranet.example.com/search?q=wiki"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/v1/opensearch/search?q={}", q))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            let location = resp.headers().get("location").unwrap().to_str().unwrap();
+
+            let req = test::TestRequest::get().uri(location).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(
+                resp.headers().get("location").unwrap().to_str().unwrap(),
+                expected
+            );
+        }
+    }
+}
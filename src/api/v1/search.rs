@@ -0,0 +1,119 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use super::links::RustlinkView;
+use crate::state::AppState;
+
+/// Results returned unless a `limit` is given
+pub const DEFAULT_RESULT_LIMIT: usize = 20;
+pub const MAX_RESULT_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResult<'a> {
+    #[serde(flatten)]
+    pub link: RustlinkView<'a>,
+    pub score: f64,
+}
+
+/// Search links by their alias, URLs, and description, ranking the most
+/// relevant (and most used) first
+#[get("")]
+pub async fn search(data: web::Data<AppState>, query: web::Query<SearchQuery>) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_RESULT_LIMIT)
+        .clamp(1, MAX_RESULT_LIMIT);
+    let rustlinks = data.rustlinks.read().await;
+
+    HttpResponse::Ok().json(
+        rustlinks
+            .search(&query.q, &data.usage, limit)
+            .into_iter()
+            .map(|(alias, rustlink, score)| SearchResult {
+                link: RustlinkView::new(alias, rustlink),
+                score,
+            })
+            .collect::<Vec<SearchResult>>(),
+    )
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{test, App};
+    use serde_json::Value;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{rustlink::Rustlink, RustlinkAlias};
+
+    #[actix_web::test]
+    async fn it_searches_links_by_relevance_and_usage() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "payments".to_string(),
+            Rustlink {
+                url: "https://wiki.example.com/teams/payments".to_string(),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "oncall".to_string(),
+            Rustlink {
+                url: "https://pager.example.com".to_string(),
+                description: Some("Who's on call for payments".to_string()),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "standup".to_string(),
+            Rustlink {
+                url: "https://meet.example.com/standup".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    usage: Arc::new(HashMap::from([("oncall".to_string(), 50)]).into()),
+                    ..AppState::for_tests()
+                }))
+                .service(web::scope("/search").service(search)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=payments")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let aliases: Vec<&str> = resp
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["alias"].as_str().unwrap())
+            .collect();
+        assert_eq!(aliases, vec!["oncall", "payments"]);
+        assert_eq!(resp[1]["url"], "https://wiki.example.com/teams/payments");
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=pay&limit=1")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.as_array().unwrap().len(), 1);
+
+        let req = test::TestRequest::get()
+            .uri("/search?q=wiki+call")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp, Value::Array(vec![]));
+    }
+}
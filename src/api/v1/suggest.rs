@@ -0,0 +1,127 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::{index::popular, state::AppState, util};
+
+/// Completions returned unless a `limit` is given
+pub const DEFAULT_SUGGESTION_LIMIT: usize = 8;
+pub const MAX_SUGGESTION_LIMIT: usize = popular::MAX_POPULAR_LINKS;
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default)]
+    prefix: String,
+    limit: Option<usize>,
+}
+
+/// An alias completing the typed prefix, and where it currently goes
+#[derive(Serialize)]
+pub struct Suggestion {
+    pub alias: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub usage: u64,
+}
+
+/// The most used links completing `prefix`
+pub(crate) async fn suggestions(
+    data: &AppState,
+    prefix: &str,
+    limit: Option<usize>,
+) -> Vec<Suggestion> {
+    let limit = limit
+        .unwrap_or(DEFAULT_SUGGESTION_LIMIT)
+        .clamp(1, MAX_SUGGESTION_LIMIT);
+    let prefix = util::canonicalize_alias(prefix, &data.alias_normalization);
+    let rustlinks = data.rustlinks.read().await;
+    let now = Utc::now();
+
+    rustlinks
+        .suggest(&prefix, &data.usage, limit)
+        .into_iter()
+        .map(|(alias, rustlink, usage)| Suggestion {
+            alias: rustlink.display_alias.clone().unwrap_or(alias.clone()),
+            url: rustlink.active_url(now).to_string(),
+            description: rustlink.description.clone(),
+            usage,
+        })
+        .collect()
+}
+
+/// Complete an alias being typed (e.g. `go/pay`), suggesting the most used
+/// links first
+#[get("")]
+pub async fn suggest(data: web::Data<AppState>, query: web::Query<SuggestQuery>) -> impl Responder {
+    HttpResponse::Ok().json(suggestions(&data, &query.prefix, query.limit).await)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{rustlink::Rustlink, util::AliasNormalization, RustlinkAlias};
+
+    #[actix_web::test]
+    async fn it_suggests_completions_by_popularity() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+
+        for alias in ["pay", "payments", "payroll", "standup"] {
+            rustlinks.insert(
+                alias.to_string(),
+                Rustlink {
+                    url: format!("https://{}.example.com", alias),
+                    display_alias: Some(alias.to_uppercase()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    alias_normalization: vec![AliasNormalization::Case],
+                    usage: Arc::new(
+                        HashMap::from([("payroll".to_string(), 7), ("standup".to_string(), 100)])
+                            .into(),
+                    ),
+                    ..AppState::for_tests()
+                }))
+                .service(web::scope("/suggest").service(suggest)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/suggest?prefix=Pay")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp,
+            json!([
+                { "alias": "PAYROLL", "url": "https://payroll.example.com", "usage": 7 },
+                { "alias": "PAY", "url": "https://pay.example.com", "usage": 0 },
+                { "alias": "PAYMENTS", "url": "https://payments.example.com", "usage": 0 },
+            ])
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/suggest?prefix=pay&limit=2")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.as_array().unwrap().len(), 2);
+        assert_eq!(resp[1]["alias"], "PAY");
+
+        let req = test::TestRequest::get()
+            .uri("/suggest?prefix=wiki")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp, json!([]));
+    }
+}
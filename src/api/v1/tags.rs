@@ -0,0 +1,118 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+
+use super::links::RustlinkView;
+use crate::{rustlink::normalize_tag, state::AppState};
+
+#[derive(Serialize)]
+pub struct TagCount<'a> {
+    pub tag: &'a str,
+    /// How many links carry the tag
+    pub count: usize,
+}
+
+/// Every tag in use, the most used first (then in tag order)
+#[get("")]
+pub async fn get_tags(data: web::Data<AppState>) -> impl Responder {
+    let rustlinks = data.rustlinks.read().await;
+    let mut tags: Vec<TagCount> = rustlinks
+        .tags()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    // Stable, so ties stay in tag order
+    tags.sort_by(|a, b| b.count.cmp(&a.count));
+
+    HttpResponse::Ok().json(tags)
+}
+
+/// The links carrying `tag`, in alias order
+#[get("/{tag}/links")]
+pub async fn get_tagged_rustlinks(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let tag = normalize_tag(&path.into_inner());
+    let rustlinks = data.rustlinks.read().await;
+
+    HttpResponse::Ok().json(
+        rustlinks
+            .tagged(&tag)
+            .map(|(alias, rustlink)| RustlinkView::new(alias, rustlink))
+            .collect::<Vec<RustlinkView>>(),
+    )
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{rustlink::Rustlink, RustlinkAlias};
+
+    #[actix_web::test]
+    async fn it_lists_tags_and_their_links() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+
+        for (alias, tags) in [
+            ("standup", vec!["meetings", "team"]),
+            ("retro", vec!["meetings"]),
+            ("oncall", vec!["incidents"]),
+        ] {
+            rustlinks.insert(
+                alias.to_string(),
+                Rustlink {
+                    url: format!("https://example.com/{}", alias),
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
+                }))
+                .service(
+                    web::scope("/tags")
+                        .service(get_tags)
+                        .service(get_tagged_rustlinks),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/tags").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp,
+            json!([
+                { "tag": "meetings", "count": 2 },
+                { "tag": "incidents", "count": 1 },
+                { "tag": "team", "count": 1 },
+            ])
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/tags/Meetings/links")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let aliases: Vec<&str> = resp
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|link| link["alias"].as_str().unwrap())
+            .collect();
+        assert_eq!(aliases, vec!["retro", "standup"]);
+
+        let req = test::TestRequest::get()
+            .uri("/tags/wiki/links")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp, json!([]));
+    }
+}
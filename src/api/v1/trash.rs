@@ -0,0 +1,337 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::links::{validate_rustlink, RustlinkView};
+use crate::{
+    oidc::identity::Identity,
+    state::AppState,
+    store::{Condition, Operation},
+    trash::TrashedRustlink,
+    util::{self, TRASH_NAMESPACE},
+};
+
+/// A deleted link as returned by the API, along with when it's purged
+#[derive(Serialize)]
+pub struct TrashedView<'a> {
+    #[serde(flatten)]
+    pub link: RustlinkView<'a>,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: Option<&'a str>,
+    pub purge_at: DateTime<Utc>,
+}
+
+fn not_found(alias: &str) -> HttpResponse {
+    HttpResponse::NotFound().body(format!("No deleted link exists for alias `{}`", alias))
+}
+
+/// Every link in the trash, the most recently deleted first
+#[get("")]
+pub async fn get_trash(data: web::Data<AppState>) -> impl Responder {
+    let values = match data.store.get_prefix(TRASH_NAMESPACE).await {
+        Ok((values, _)) => values,
+        Err(e) => {
+            eprintln!("Failed to GET trash from etcd: {:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    let trashed: Vec<(&str, TrashedRustlink)> = values
+        .iter()
+        .filter_map(|value| {
+            let alias = value.key.strip_prefix(TRASH_NAMESPACE)?;
+
+            serde_json::from_slice(&value.value)
+                .map_err(|e| eprintln!("Failed to parse trashed link at {}: {:?}", value.key, e))
+                .ok()
+                .map(|trashed| (alias, trashed))
+        })
+        .collect();
+    let mut views: Vec<TrashedView> = trashed
+        .iter()
+        .map(|(alias, trashed)| TrashedView {
+            link: RustlinkView::new(alias, &trashed.rustlink),
+            deleted_at: trashed.deleted_at,
+            deleted_by: trashed.deleted_by.as_deref(),
+            purge_at: trashed.purge_at(data.trash_retention),
+        })
+        .collect();
+    views.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    HttpResponse::Ok().json(views)
+}
+
+/// Move the link at `alias` out of the trash, as long as no link has been
+/// created at its alias since it was deleted
+#[post("/{alias:.+}/restore")]
+pub async fn restore_rustlink(
+    data: web::Data<AppState>,
+    identity: Option<Identity>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let alias = path.into_inner();
+    let trash_key = util::alias_to_trash_key(&alias, &data.alias_normalization);
+    let key = util::alias_to_key(&alias, &data.alias_normalization);
+
+    let stored = match data.store.get(&trash_key).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return not_found(&alias),
+        Err(e) => {
+            eprintln!("Failed to GET from etcd: {:?}", e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    let trashed = match serde_json::from_slice::<TrashedRustlink>(&stored.value) {
+        Ok(trashed) => trashed.rustlink,
+        Err(e) => {
+            eprintln!("Failed to parse trashed link at {}: {:?}", trash_key, e);
+            return HttpResponse::InternalServerError().body("Internal Server Error");
+        }
+    };
+    // The links may have changed since it was deleted (as may what's reserved),
+    // so it's validated like any other write, keeping its original spelling
+    let spelling = trashed.display_alias.clone().unwrap_or(alias.clone());
+
+    if let Some(reserved) = data.reserved.check(&spelling) {
+        return HttpResponse::BadRequest().body(format!(
+            "Alias `{}` is reserved for {}",
+            spelling, reserved.reason
+        ));
+    }
+    let validated = validate_rustlink(
+        &data,
+        &*data.rustlinks.read().await,
+        &spelling,
+        trashed.clone(),
+        Some(&trashed),
+        identity.as_ref(),
+    );
+    let (mut rustlink, bytes) = match validated {
+        Ok(validated) => validated,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let restored = data
+        .store
+        .txn(
+            vec![
+                Condition::ModRevision(trash_key.clone(), stored.mod_revision),
+                Condition::Absent(key.clone()),
+            ],
+            vec![
+                Operation::Put(key.clone(), bytes),
+                Operation::Delete(trash_key),
+            ],
+        )
+        .await;
+
+    match restored {
+        Ok(Some(revision)) => {
+            rustlink.revision = Some(revision);
+            let canonical = util::canonicalize_alias(&alias, &data.alias_normalization);
+
+            HttpResponse::Ok().json(RustlinkView::new(&canonical, &rustlink))
+        }
+        Ok(None) => HttpResponse::Conflict().body(format!(
+            "A link has been created for alias `{}` since it was deleted, or it's no longer in the trash",
+            alias
+        )),
+        Err(e) => {
+            eprintln!("Failed to restore link in etcd: {:?}", e);
+            HttpResponse::InternalServerError().body("Internal Server Error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        api::v1::links::{delete_rustlink, get_rustlink},
+        rustlink::Rustlink,
+        store::{memory::InMemoryStore, Store},
+        util::AliasNormalization,
+    };
+
+    #[actix_web::test]
+    async fn it_restores_deleted_links_from_the_trash() {
+        let store = Arc::new(InMemoryStore::default());
+        let oncall = Rustlink {
+            url: "https://example.com/oncall".to_string(),
+            ..Default::default()
+        };
+        store
+            .put(
+                util::alias_to_key("oncall", &[]),
+                serde_json::to_vec(&oncall).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    store: store.clone(),
+                    alias_normalization: vec![AliasNormalization::Case],
+                    ..AppState::for_tests()
+                }))
+                .service(
+                    web::scope("/links")
+                        .service(get_rustlink)
+                        .service(delete_rustlink),
+                )
+                .service(
+                    web::scope("/trash")
+                        .service(get_trash)
+                        .service(restore_rustlink),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/links/OnCall")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/links/oncall").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/trash").to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp[0]["alias"], "oncall");
+        assert_eq!(resp[0]["url"], "https://example.com/oncall");
+        let deleted_at: DateTime<Utc> =
+            serde_json::from_value(resp[0]["deleted_at"].clone()).unwrap();
+        let purge_at: DateTime<Utc> = serde_json::from_value(resp[0]["purge_at"].clone()).unwrap();
+        assert_eq!(purge_at - deleted_at, chrono::Duration::days(30));
+
+        let req = test::TestRequest::post()
+            .uri("/trash/ONCALL/restore")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["url"], "https://example.com/oncall");
+
+        let req = test::TestRequest::get().uri("/links/oncall").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::post()
+            .uri("/trash/oncall/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Links aren't restored over ones created since they were deleted
+        let req = test::TestRequest::delete()
+            .uri("/links/oncall")
+            .to_request();
+        test::call_service(&app, req).await;
+        store
+            .put(
+                util::alias_to_key("oncall", &[]),
+                serde_json::to_vec(&oncall).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/trash/oncall/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn it_validates_links_restored_from_the_trash() {
+        let store = Arc::new(InMemoryStore::default());
+        let standup = Rustlink {
+            url: "alias:meet".to_string(),
+            updated_at: Some(Utc::now() - chrono::Duration::days(7)),
+            ..Default::default()
+        };
+        for (alias, rustlink) in [
+            (
+                "meet",
+                Rustlink {
+                    url: "https://meet.example.com".to_string(),
+                    ..Default::default()
+                },
+            ),
+            ("standup", standup.clone()),
+        ] {
+            store
+                .put(
+                    util::alias_to_key(alias, &[]),
+                    serde_json::to_vec(&rustlink).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+        let state = AppState {
+            store: store.clone(),
+            ..AppState::for_tests()
+        };
+        {
+            let mut rustlinks = state.rustlinks.write().await;
+            rustlinks.insert(
+                "meet".to_string(),
+                Rustlink {
+                    url: "https://meet.example.com".to_string(),
+                    ..Default::default()
+                },
+            );
+            rustlinks.insert("standup".to_string(), standup);
+        }
+        let data = web::Data::new(state);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .service(web::scope("/links").service(delete_rustlink))
+                .service(web::scope("/trash").service(restore_rustlink)),
+        )
+        .await;
+
+        for alias in ["standup", "meet"] {
+            let req = test::TestRequest::delete()
+                .uri(&format!("/links/{}", alias))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            data.rustlinks.write().await.remove(alias);
+        }
+
+        // `standup` would refer to a link which no longer exists
+        let req = test::TestRequest::post()
+            .uri("/trash/standup/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/trash/meet/restore")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        data.rustlinks.write().await.insert(
+            "meet".to_string(),
+            Rustlink {
+                url: "https://meet.example.com".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let before = Utc::now();
+        let req = test::TestRequest::post()
+            .uri("/trash/standup/restore")
+            .to_request();
+        let resp: Value = test::call_and_read_body_json(&app, req).await;
+        let updated_at: DateTime<Utc> = serde_json::from_value(resp["updated_at"].clone()).unwrap();
+        assert!(updated_at >= before);
+    }
+}
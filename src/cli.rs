@@ -3,7 +3,11 @@ use std::{path::PathBuf, str::FromStr, string::ParseError};
 use clap::{Args, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 
-use crate::{errors::RustlinksError, oidc, util::password_prompt};
+use crate::{
+    errors::RustlinksError,
+    fallback, oidc, shortcode, template, trash,
+    util::{password_prompt, AliasNormalization},
+};
 
 /// A simple application for managing short links
 /// For debug logs, set RUST_LOG=debug
@@ -75,6 +79,15 @@ pub enum Commands {
         #[arg(short, long, default_value = "8080")]
         port: u16,
 
+        /// URL users reach the server at, which differs from the address it
+        /// binds to behind a reverse proxy. URLs handed to browsers (e.g. for
+        /// OpenSearch) are made from it, and link templates get its host as
+        /// `{$host}`.
+        ///
+        /// Example: --public-url https://go.example.com
+        #[arg(long, default_value = "https://rs")]
+        public_url: String,
+
         /// Path to a directory to persist Rustlink data
         #[arg(long, default_value = ".rustlinks/")]
         data_dir: PathBuf,
@@ -109,6 +122,62 @@ pub enum Commands {
         /// `/api/v1/rustlinks` endpoints will be guarded by OIDC authentication
         #[arg(long, num_args = 0..)]
         oidc_providers: Vec<oidc::provider::OIDCProvider>,
+
+        /// Normalizations applied to aliases when storing and resolving links,
+        /// comma-separated. Every read-write server sharing an etcd namespace
+        /// should use the same set.
+        ///
+        /// Example: --alias-normalization nfkc,case,separators
+        #[arg(long, value_enum, value_delimiter = ',')]
+        alias_normalization: Vec<AliasNormalization>,
+
+        /// Fallbacks tried in order when no alias matches a request. Either a
+        /// URL template (e.g. an intranet search) which always resolves, or
+        /// `upstream:<url>` to ask another rustlinks server, moving on to the
+        /// next fallback if it can't resolve the alias either.
+        ///
+        /// Example: --fallback-url upstream:https://go.example.com
+        /// --fallback-url "https://intranet.example.com/search?q={^}"
+        #[arg(long)]
+        fallback_url: Vec<fallback::Fallback>,
+
+        /// Secret shared with the upstream servers this one falls back to
+        /// (and those falling back to it), sent to ask each other not to use
+        /// their own fallbacks. Without one, fallbacks are always used, so
+        /// servers falling back to each other must share one.
+        #[arg(long)]
+        fallback_secret: Option<String>,
+
+        /// Users whose personal links (`go/~/...`) are synced to this node,
+        /// identified by email (or subject, for providers which don't share
+        /// emails). Other users' personal links are fetched when they're used.
+        ///
+        /// Example: --personal-links ada@example.com
+        #[arg(long)]
+        personal_links: Vec<String>,
+
+        /// Values available to link templates as `{$config.<name>}`, given as
+        /// name=value
+        ///
+        /// Example: --template-variable wiki=https://wiki.example.com
+        #[arg(long)]
+        template_variable: Vec<template::TemplateVariable>,
+
+        /// Minimum length of the codes minted for links created without an
+        /// alias. Codes get longer as they collide with existing aliases.
+        #[arg(long, default_value_t = shortcode::DEFAULT_MIN_LENGTH)]
+        short_code_min_length: usize,
+
+        /// Characters the codes minted for links created without an alias are
+        /// made of. Every character must be left unchanged by the configured
+        /// alias normalizations.
+        #[arg(long, default_value = shortcode::DEFAULT_ALPHABET)]
+        short_code_alphabet: String,
+
+        /// Days deleted links are kept in the trash, from which they can be
+        /// restored, before they're purged
+        #[arg(long, default_value_t = trash::DEFAULT_RETENTION_DAYS)]
+        trash_retention_days: u32,
     },
     /// Setup the application, automatically performs certificate
     /// generation, etcd role+user provisioning, and other setup required for
@@ -171,12 +240,21 @@ mod unit_tests {
             command: Commands::Start {
                 hostname: "".to_string(),
                 port: 0,
+                public_url: "".to_string(),
                 data_dir: PathBuf::from(""),
                 cert_file: None,
                 key_file: None,
                 oidc_providers: vec![],
                 oauth_redirect_uri: "".to_string(),
                 login_path: "".to_string(),
+                alias_normalization: vec![],
+                fallback_url: vec![],
+                fallback_secret: None,
+                personal_links: vec![],
+                template_variable: vec![],
+                short_code_min_length: 0,
+                short_code_alphabet: "".to_string(),
+                trash_retention_days: 0,
             },
         };
         let serialized = serde_json::to_string(&opts).unwrap();
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::util::{self, AliasNormalization};
+
+/// A named, ordered list of links, e.g. a team's landing page
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Collection {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The aliases of the links in the collection, in the order they're shown
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Who to ask about the collection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// The name as originally spelled when the collection was created, as the
+    /// key it's stored under may have been canonicalized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The etcd revision the collection was last modified at, set when it's
+    /// synced rather than stored with the collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+}
+
+/// Collections by their canonical name
+pub type Collections = HashMap<String, Collection>;
+
+/// Apply a collection stored at a key with the given name (as spelled in the
+/// key) and last modified at `revision`
+pub fn apply_put(
+    collections: &mut Collections,
+    key_name: &str,
+    value: &[u8],
+    revision: i64,
+    normalizations: &[AliasNormalization],
+) -> Result<(), serde_json::Error> {
+    let mut collection = serde_json::from_slice::<Collection>(value)?;
+    collection
+        .display_name
+        .get_or_insert_with(|| key_name.to_string());
+    collection.revision = Some(revision);
+    collections.insert(
+        util::canonicalize_alias(key_name, normalizations),
+        collection,
+    );
+    Ok(())
+}
+
+/// Apply the deletion of a collection stored at a key with the given name
+pub fn apply_delete(
+    collections: &mut Collections,
+    key_name: &str,
+    normalizations: &[AliasNormalization],
+) {
+    collections.remove(&util::canonicalize_alias(key_name, normalizations));
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_applies_stored_collections_by_canonical_name() {
+        let mut collections = Collections::new();
+        apply_put(
+            &mut collections,
+            "Payments",
+            br#"{"title":"Payments team","aliases":["payments/runbook","oncall"]}"#,
+            3,
+            &[AliasNormalization::Case],
+        )
+        .unwrap();
+
+        let collection = &collections["payments"];
+        assert_eq!(collection.aliases, vec!["payments/runbook", "oncall"]);
+        assert_eq!(collection.display_name.as_deref(), Some("Payments"));
+        assert_eq!(collection.revision, Some(3));
+
+        apply_delete(&mut collections, "PAYMENTS", &[AliasNormalization::Case]);
+        assert!(collections.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use std;
 
+use actix_web::{http::StatusCode, ResponseError};
 use etcd_rs;
 use thiserror::Error;
 
@@ -24,3 +25,47 @@ pub enum RustlinksError {
     #[error("unknown oidc provider: {0}")]
     UnknownOIDCProvider(String),
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ReferenceError {
+    #[error("alias references form a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("alias references nested deeper than {0} levels")]
+    TooDeep(usize),
+    #[error("referenced alias does not exist: {0}")]
+    Dangling(String),
+}
+
+impl ResponseError for ReferenceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ReferenceError::Cycle(_) | ReferenceError::TooDeep(_) => StatusCode::LOOP_DETECTED,
+            ReferenceError::Dangling(_) => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("unknown template variable: {0}")]
+    UnknownVariable(String),
+    #[error("invalid format for template variable {0}: {1}")]
+    InvalidFormat(String, String),
+    #[error("template variable {0} does not take a format")]
+    UnexpectedFormat(String),
+    #[error("unclosed template variable starting at {0}")]
+    Unclosed(usize),
+    #[error("only http(s) URLs (or `alias:` references) are supported: {0}")]
+    UnsupportedScheme(String),
+    #[error("sign in to follow this link, as where it goes depends on who you are")]
+    Unidentified,
+}
+
+impl ResponseError for TemplateError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TemplateError::Unidentified => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::http::header::{HeaderMap, LOCATION};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use urlencoding::encode;
+
+use crate::{redirect::render_url_template, template};
+
+/// Header sent when asking an upstream server to resolve an alias, telling it
+/// not to use its own fallbacks (which also prevents servers falling back to
+/// each other from looping). Its value is the secret the servers share, so
+/// that other clients can't turn fallbacks off.
+pub const NO_FALLBACK_HEADER: &str = "x-rustlinks-no-fallback";
+
+const UPSTREAM_PREFIX: &str = "upstream:";
+
+/// How long an upstream failing to resolve an alias is remembered, so that
+/// repeated misses don't each wait on it
+const UPSTREAM_MISS_TTL: Duration = Duration::from_secs(60);
+
+/// Upstream misses remembered at most, as the paths missed are up to clients
+const MAX_UPSTREAM_MISSES: usize = 10_000;
+
+lazy_static! {
+    static ref UPSTREAM_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(2))
+        .build()
+        .unwrap();
+    /// When each upstream URL last failed to resolve
+    static ref UPSTREAM_MISSES: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Whether a request was sent by an upstream server asking us not to use our
+/// fallbacks, which only servers sharing `secret` can do
+pub fn skips_fallbacks(headers: &HeaderMap, secret: Option<&str>) -> bool {
+    match (headers.get(NO_FALLBACK_HEADER), secret) {
+        (Some(sent), Some(secret)) => {
+            let (sent, secret) = (sent.as_bytes(), secret.as_bytes());
+
+            // Compared in constant time, so the secret can't be guessed byte
+            // by byte
+            sent.len() == secret.len()
+                && sent
+                    .iter()
+                    .zip(secret)
+                    .fold(0, |differs, (a, b)| differs | (a ^ b))
+                    == 0
+        }
+        _ => false,
+    }
+}
+
+fn recently_missed(url: &str) -> bool {
+    let misses = UPSTREAM_MISSES.lock().unwrap();
+    misses
+        .get(url)
+        .is_some_and(|missed| missed.elapsed() < UPSTREAM_MISS_TTL)
+}
+
+fn remember_miss(url: String) {
+    let mut misses = UPSTREAM_MISSES.lock().unwrap();
+
+    if misses.len() >= MAX_UPSTREAM_MISSES {
+        misses.retain(|_, missed| missed.elapsed() < UPSTREAM_MISS_TTL);
+
+        if misses.len() >= MAX_UPSTREAM_MISSES {
+            misses.clear();
+        }
+    }
+    misses.insert(url, Instant::now());
+}
+
+/// Where to send requests for aliases which don't exist
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Fallback {
+    /// Ask another rustlinks server to resolve the alias, moving on to the
+    /// next fallback if it can't
+    Upstream(String),
+    /// A URL template, which always resolves
+    Template(String),
+}
+
+impl FromStr for Fallback {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.strip_prefix(UPSTREAM_PREFIX) {
+            Some(upstream) => match url::Url::parse(upstream) {
+                Ok(_) => Ok(Fallback::Upstream(
+                    upstream.trim_end_matches('/').to_string(),
+                )),
+                Err(_) => Err("Invalid URL passed for upstream fallback"),
+            },
+            None => Ok(Fallback::Template(value.to_string())),
+        }
+    }
+}
+
+impl Fallback {
+    /// Resolve the full request (alias followed by any space-separated
+    /// parameters) to a URL, if possible. Upstream servers are sent `secret`
+    /// to ask them not to use their own fallbacks. URLs which aren't web pages
+    /// aren't resolved, like those of links.
+    pub async fn resolve(&self, requested: &str, secret: Option<&str>) -> Option<String> {
+        match self {
+            Fallback::Template(template) => Some(render_url_template(template, Some(requested)))
+                .filter(|url| template::check_scheme(url).is_ok()),
+            Fallback::Upstream(upstream) => {
+                let path = requested
+                    .split('/')
+                    .map(|segment| encode(segment).into_owned())
+                    .collect::<Vec<String>>()
+                    .join("/");
+                let url = format!("{}/{}", upstream, path);
+
+                if recently_missed(&url) {
+                    return None;
+                }
+                let mut request = UPSTREAM_CLIENT.get(&url);
+
+                if let Some(secret) = secret {
+                    request = request.header(NO_FALLBACK_HEADER, secret);
+                }
+                let resolved = match request.send().await {
+                    Ok(response) if response.status().is_redirection() => response
+                        .headers()
+                        .get(LOCATION.as_str())
+                        .and_then(|location| location.to_str().ok())
+                        .filter(|location| template::check_scheme(location).is_ok())
+                        .map(str::to_string),
+                    Ok(_) => None,
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to resolve alias using upstream {}: {:?}",
+                            upstream, e
+                        );
+                        None
+                    }
+                };
+
+                if resolved.is_none() {
+                    remember_miss(url);
+                }
+                resolved
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_fallbacks() {
+        assert_eq!(
+            Fallback::from_str("upstream:https://go.example.com/"),
+            Ok(Fallback::Upstream("https://go.example.com".to_string()))
+        );
+        assert_eq!(
+            Fallback::from_str("https://intranet.example.com/search?q={^}"),
+            Ok(Fallback::Template(
+                "https://intranet.example.com/search?q={^}".to_string()
+            ))
+        );
+        assert!(Fallback::from_str("upstream:notevenavalidurl").is_err());
+    }
+
+    #[actix_web::test]
+    async fn it_resolves_templates_with_the_requested_alias() {
+        let fallback = Fallback::Template("https://intranet.example.com/search?q={^}".to_string());
+        assert_eq!(
+            fallback.resolve("payments", None).await,
+            Some("https://intranet.example.com/search?q=payments".to_string())
+        );
+    }
+
+    #[test]
+    fn it_only_skips_fallbacks_for_servers_sharing_the_secret() {
+        let mut headers = HeaderMap::new();
+        assert!(!skips_fallbacks(&headers, Some("s3cret")));
+
+        headers.insert(
+            NO_FALLBACK_HEADER.try_into().unwrap(),
+            "s3cret".try_into().unwrap(),
+        );
+        assert!(skips_fallbacks(&headers, Some("s3cret")));
+        assert!(!skips_fallbacks(&headers, Some("other")));
+        assert!(!skips_fallbacks(&headers, None));
+    }
+
+    #[actix_web::test]
+    async fn it_remembers_upstream_misses() {
+        // Nothing listens on the discard port, so the upstream fails fast
+        let fallback = Fallback::Upstream("http://127.0.0.1:9".to_string());
+        assert_eq!(fallback.resolve("payments", None).await, None);
+        assert!(recently_missed("http://127.0.0.1:9/payments"));
+    }
+
+    #[actix_web::test]
+    async fn it_ignores_upstream_redirects_to_anything_but_web_pages() {
+        use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: javascript:alert(1)\r\nContent-Length: 0\r\n\r\n",
+                )
+                .await;
+        });
+
+        let fallback = Fallback::Upstream(upstream.clone());
+        assert_eq!(fallback.resolve("payments", None).await, None);
+        assert!(recently_missed(&format!("{}/payments", upstream)));
+
+        let fallback = Fallback::Template("javascript:{^}".to_string());
+        assert_eq!(fallback.resolve("alert(1)", None).await, None);
+    }
+}
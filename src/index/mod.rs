@@ -0,0 +1,734 @@
+pub mod ngrams;
+pub mod overlay;
+pub mod popular;
+pub mod prefix;
+pub mod search;
+pub mod tags;
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeSet, BinaryHeap, HashMap},
+    ops::{Bound, Deref},
+};
+
+use crate::{
+    errors::ReferenceError,
+    rustlink::Rustlink,
+    usage::Usage,
+    util::{self, AliasNormalization},
+    RustlinkAlias,
+};
+
+/// Minimum normalized edit-distance similarity (0 to 1) for an alias to be
+/// suggested in place of one which doesn't exist
+const MIN_SUGGESTION_SIMILARITY: f64 = 0.5;
+
+/// Aliases compared by edit distance when suggesting ones similar to an alias
+/// which doesn't exist, picked by the trigrams they share with it
+const MAX_SUGGESTION_CANDIDATES: usize = 64;
+
+/// Maximum number of `alias:` references followed when resolving a link
+pub const MAX_REFERENCE_DEPTH: usize = 8;
+
+/// The in-memory set of links, along with any indices derived from them.
+/// Dereferences to the underlying map for reads, while writes go through
+/// [`RustlinkIndex::insert`] and [`RustlinkIndex::remove`] to keep the
+/// indices up to date.
+#[derive(Clone, Debug, Default)]
+pub struct RustlinkIndex {
+    links: HashMap<RustlinkAlias, Rustlink>,
+    prefixes: prefix::PrefixIndex,
+    /// Every alias in order, so pages of links can be listed without sorting
+    /// all of them
+    ordered: BTreeSet<RustlinkAlias>,
+    /// Every alias by the revision its link was last modified at, most recent
+    /// first, so pages of recently modified links don't need sorting either
+    modified: BTreeSet<(Reverse<i64>, RustlinkAlias)>,
+    search: search::SearchIndex,
+    tags: tags::TagIndex,
+    ngrams: ngrams::NgramIndex,
+    popular: popular::PopularIndex,
+}
+
+impl RustlinkIndex {
+    pub fn insert(&mut self, alias: RustlinkAlias, rustlink: Rustlink) -> Option<Rustlink> {
+        self.prefixes.insert(&alias);
+        self.ordered.insert(alias.clone());
+        self.search.insert(&alias, &rustlink);
+        self.ngrams.insert(&alias);
+
+        match self.links.get(&alias) {
+            Some(existing) => {
+                self.tags.remove(&alias, &existing.tags);
+                self.modified.remove(&(
+                    Reverse(existing.revision.unwrap_or_default()),
+                    alias.clone(),
+                ));
+            }
+            None => self.popular.forget(&alias),
+        }
+        self.tags.insert(&alias, &rustlink.tags);
+        self.modified.insert((
+            Reverse(rustlink.revision.unwrap_or_default()),
+            alias.clone(),
+        ));
+        self.links.insert(alias, rustlink)
+    }
+
+    pub fn remove(&mut self, alias: &str) -> Option<Rustlink> {
+        self.prefixes.remove(alias);
+        self.ordered.remove(alias);
+        self.search.remove(alias);
+        self.ngrams.remove(alias);
+
+        if let Some(existing) = self.links.get(alias) {
+            self.tags.remove(alias, &existing.tags);
+            self.modified.remove(&(
+                Reverse(existing.revision.unwrap_or_default()),
+                alias.to_string(),
+            ));
+        }
+        self.popular.forget(alias);
+        self.links.remove(alias)
+    }
+
+    /// Every tag in order, along with how many links carry it
+    pub fn tags(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.tags.counts()
+    }
+
+    /// The links carrying `tag`, in alias order
+    pub fn tagged<'a>(
+        &'a self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = (&'a RustlinkAlias, &'a Rustlink)> + 'a {
+        self.tags
+            .aliases(tag)
+            .filter_map(|alias| self.links.get_key_value(alias))
+    }
+
+    /// The most used links whose alias completes `prefix`, most used first
+    /// (then in alias order), along with their usage
+    pub fn suggest<'a>(
+        &'a self,
+        prefix: &'a str,
+        usage: &Usage,
+        limit: usize,
+    ) -> Vec<(&'a RustlinkAlias, &'a Rustlink, u64)> {
+        // Most links complete short prefixes, so rather than going through all
+        // of them their most used ones are remembered
+        if !popular::PopularIndex::remembers(prefix) || limit > popular::MAX_POPULAR_LINKS {
+            return self.popular(prefix, usage, None, limit);
+        }
+        let aliases = self.popular.get(prefix, || {
+            self.popular(prefix, usage, None, popular::MAX_POPULAR_LINKS)
+                .into_iter()
+                .map(|(alias, _, _)| alias.clone())
+                .collect()
+        });
+        let mut suggestions: Vec<(&RustlinkAlias, &Rustlink, u64)> = aliases
+            .iter()
+            .filter_map(|alias| self.links.get_key_value(alias))
+            .map(|(alias, rustlink)| (alias, rustlink, usage.get(alias)))
+            .collect();
+
+        suggestions.sort_by(|(a_alias, _, a), (b_alias, _, b)| {
+            b.cmp(a).then_with(|| a_alias.cmp(b_alias))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Like [`RustlinkIndex::suggest`], but starting after the link with the
+    /// given usage and alias if given. Usage isn't indexed as it changes with
+    /// every redirect, so only the best `limit` links are kept while scanning
+    /// `prefix` rather than sorting all of them.
+    pub fn popular<'a>(
+        &'a self,
+        prefix: &'a str,
+        usage: &Usage,
+        after: Option<(u64, &str)>,
+        limit: usize,
+    ) -> Vec<(&'a RustlinkAlias, &'a Rustlink, u64)> {
+        // Keep the best `limit` links seen so far, with the worst on top of the
+        // heap so it can be evicted
+        let mut best: BinaryHeap<(Reverse<u64>, &RustlinkAlias)> = BinaryHeap::new();
+
+        for (alias, _) in self.range(prefix, None) {
+            let used = usage.get(alias);
+
+            if after.is_some_and(|(after_used, after)| {
+                (Reverse(used), alias.as_str()) <= (Reverse(after_used), after)
+            }) {
+                continue;
+            }
+            best.push((Reverse(used), alias));
+
+            if best.len() > limit {
+                best.pop();
+            }
+        }
+        best.into_sorted_vec()
+            .into_iter()
+            .filter_map(|(Reverse(used), alias)| {
+                self.links
+                    .get(alias)
+                    .map(|rustlink| (alias, rustlink, used))
+            })
+            .collect()
+    }
+
+    /// Links matching `query`, ranked by their relevance boosted by how often
+    /// they're used, along with their score
+    pub fn search(
+        &self,
+        query: &str,
+        usage: &Usage,
+        limit: usize,
+    ) -> Vec<(&RustlinkAlias, &Rustlink, f64)> {
+        let mut scored: Vec<(&RustlinkAlias, &Rustlink, f64)> = self
+            .search
+            .search(query)
+            .into_iter()
+            .filter_map(|(alias, relevance)| {
+                let rustlink = self.links.get(alias)?;
+                // Logarithmic, so that heavily used links don't drown out
+                // better matches
+                let used = usage.get(alias) as f64;
+                Some((alias, rustlink, relevance * (1.0 + used.ln_1p())))
+            })
+            .collect();
+
+        scored.sort_by(|(a_alias, _, a), (b_alias, _, b)| {
+            b.partial_cmp(a)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a_alias.cmp(b_alias))
+        });
+        scored.truncate(limit);
+        scored
+    }
+
+    /// Links whose alias starts with `prefix`, in alias order, starting after
+    /// the alias `after` if given
+    pub fn range<'a>(
+        &'a self,
+        prefix: &'a str,
+        after: Option<&str>,
+    ) -> impl Iterator<Item = (&'a RustlinkAlias, &'a Rustlink)> + 'a {
+        let start = match after {
+            Some(after) if after >= prefix => Bound::Excluded(after),
+            _ => Bound::Included(prefix),
+        };
+        self.ordered
+            .range::<str, _>((start, Bound::Unbounded))
+            .take_while(move |alias| alias.starts_with(prefix))
+            .filter_map(|alias| self.links.get_key_value(alias))
+    }
+
+    /// Links whose alias starts with `prefix`, most recently modified first
+    /// (then in alias order), starting after the link with the given revision
+    /// and alias if given
+    pub fn recently_modified<'a>(
+        &'a self,
+        prefix: &'a str,
+        after: Option<(i64, &str)>,
+    ) -> impl Iterator<Item = (&'a RustlinkAlias, &'a Rustlink)> + 'a {
+        let start = match after {
+            Some((revision, alias)) => Bound::Excluded((Reverse(revision), alias.to_string())),
+            None => Bound::Unbounded,
+        };
+        self.modified
+            .range((start, Bound::Unbounded))
+            .filter(move |(_, alias)| alias.starts_with(prefix))
+            .filter_map(|(_, alias)| self.links.get_key_value(alias))
+    }
+
+    /// Apply a link stored at a key with the given alias (as spelled in the
+    /// key) and last modified at `revision`
+    pub fn apply_put(
+        &mut self,
+        key_alias: &str,
+        value: &[u8],
+        revision: i64,
+        normalizations: &[AliasNormalization],
+    ) -> Result<(), serde_json::Error> {
+        let mut rustlink = serde_json::from_slice::<Rustlink>(value)?;
+        // Links written before canonicalization existed are displayed as
+        // they're spelled in their key
+        rustlink
+            .display_alias
+            .get_or_insert_with(|| key_alias.to_string());
+        rustlink.revision = Some(revision);
+
+        self.insert(
+            util::canonicalize_alias(key_alias, normalizations),
+            rustlink,
+        );
+        Ok(())
+    }
+
+    /// Apply the deletion of a link stored at a key with the given alias
+    pub fn apply_delete(&mut self, key_alias: &str, normalizations: &[AliasNormalization]) {
+        self.remove(&util::canonicalize_alias(key_alias, normalizations));
+    }
+
+    /// Resolve `path` to the link with the longest alias prefixing it,
+    /// returning the alias, link, and whatever remains of the path
+    pub fn longest_prefix<'a>(&self, path: &'a str) -> Option<(&'a str, &Rustlink, &'a str)> {
+        let (alias, remainder) = self.prefixes.longest_prefix(path)?;
+        self.links
+            .get(alias)
+            .map(|rustlink| (alias, rustlink, remainder))
+    }
+
+    /// The (at most `limit`) aliases most similar to `alias` by edit distance,
+    /// most similar first. Only aliases sharing a trigram with `alias` are
+    /// compared, as misses can be for any path at all.
+    pub fn closest(&self, alias: &str, limit: usize) -> Vec<(&RustlinkAlias, &Rustlink)> {
+        let mut scored: Vec<(f64, &RustlinkAlias, &Rustlink)> = self
+            .ngrams
+            .candidates(alias, MAX_SUGGESTION_CANDIDATES)
+            .into_iter()
+            .filter_map(|candidate| self.links.get_key_value(candidate))
+            .map(|(candidate, rustlink)| {
+                let similarity = strsim::normalized_damerau_levenshtein(alias, candidate);
+                (similarity, candidate, rustlink)
+            })
+            .filter(|(similarity, _, _)| *similarity >= MIN_SUGGESTION_SIMILARITY)
+            .collect();
+
+        scored.sort_by(|(a, a_alias, _), (b, b_alias, _)| {
+            b.partial_cmp(a)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a_alias.cmp(b_alias))
+        });
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, alias, rustlink)| (alias, rustlink))
+            .collect()
+    }
+}
+
+/// Links looked up by canonical alias, whether those of an index or those a
+/// batch of changes to it would leave
+pub trait Links {
+    fn link(&self, alias: &str) -> Option<&Rustlink>;
+
+    /// Follow `alias:` references starting from `rustlink` (stored, or about to
+    /// be stored, at `alias`), returning the alias and link at the end of the
+    /// chain which has an actual destination
+    fn follow_references<'a>(
+        &'a self,
+        alias: &str,
+        rustlink: &'a Rustlink,
+        normalizations: &[AliasNormalization],
+    ) -> Result<(RustlinkAlias, &'a Rustlink), ReferenceError> {
+        let mut chain: Vec<RustlinkAlias> = vec![alias.to_string()];
+        let mut current = rustlink;
+
+        while let Some(target) = current.reference() {
+            let target = util::canonicalize_alias(target, normalizations);
+
+            if chain.contains(&target) {
+                chain.push(target);
+                return Err(ReferenceError::Cycle(chain));
+            }
+            if chain.len() > MAX_REFERENCE_DEPTH {
+                return Err(ReferenceError::TooDeep(MAX_REFERENCE_DEPTH));
+            }
+            current = self
+                .link(&target)
+                .ok_or_else(|| ReferenceError::Dangling(target.clone()))?;
+            chain.push(target);
+        }
+        Ok((chain.pop().unwrap(), current))
+    }
+
+    /// Check that writing `rustlink` at `alias` wouldn't leave any of the
+    /// aliases it refers to, whether now or once a schedule changes, in a
+    /// cycle, too deep or dangling
+    fn check_references(
+        &self,
+        alias: &str,
+        rustlink: &Rustlink,
+        normalizations: &[AliasNormalization],
+    ) -> Result<(), ReferenceError> {
+        check_chain(self, &mut vec![alias.to_string()], rustlink, normalizations)
+    }
+}
+
+fn check_chain<L: Links + ?Sized>(
+    links: &L,
+    chain: &mut Vec<RustlinkAlias>,
+    rustlink: &Rustlink,
+    normalizations: &[AliasNormalization],
+) -> Result<(), ReferenceError> {
+    for target in rustlink.references() {
+        let target = util::canonicalize_alias(target, normalizations);
+
+        if chain.contains(&target) {
+            let mut cycle = chain.clone();
+            cycle.push(target);
+            return Err(ReferenceError::Cycle(cycle));
+        }
+        if chain.len() > MAX_REFERENCE_DEPTH {
+            return Err(ReferenceError::TooDeep(MAX_REFERENCE_DEPTH));
+        }
+        let next = links
+            .link(&target)
+            .ok_or_else(|| ReferenceError::Dangling(target.clone()))?;
+        chain.push(target);
+        check_chain(links, chain, next, normalizations)?;
+        chain.pop();
+    }
+    Ok(())
+}
+
+impl Links for RustlinkIndex {
+    fn link(&self, alias: &str) -> Option<&Rustlink> {
+        self.links.get(alias)
+    }
+}
+
+impl Deref for RustlinkIndex {
+    type Target = HashMap<RustlinkAlias, Rustlink>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.links
+    }
+}
+
+impl Extend<(RustlinkAlias, Rustlink)> for RustlinkIndex {
+    fn extend<T: IntoIterator<Item = (RustlinkAlias, Rustlink)>>(&mut self, iter: T) {
+        for (alias, rustlink) in iter {
+            self.insert(alias, rustlink);
+        }
+    }
+}
+
+impl From<HashMap<RustlinkAlias, Rustlink>> for RustlinkIndex {
+    fn from(links: HashMap<RustlinkAlias, Rustlink>) -> Self {
+        let mut index = RustlinkIndex::default();
+        index.extend(links);
+        index
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::rustlink::ScheduledTarget;
+
+    #[test]
+    fn it_lists_ranges_of_aliases_in_order() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["payments/runbook", "standup", "payments", "oncall", "pay"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        let range = |index: &RustlinkIndex, prefix, after| {
+            index
+                .range(prefix, after)
+                .map(|(alias, _)| alias.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            range(&index, "", None),
+            vec!["oncall", "pay", "payments", "payments/runbook", "standup"]
+        );
+        assert_eq!(
+            range(&index, "pay", None),
+            vec!["pay", "payments", "payments/runbook"]
+        );
+        assert_eq!(
+            range(&index, "pay", Some("payments")),
+            vec!["payments/runbook"]
+        );
+        assert_eq!(
+            range(&index, "pay", Some("oncall")),
+            vec!["pay", "payments", "payments/runbook"]
+        );
+        assert_eq!(range(&index, "", Some("payments/runbook")), vec!["standup"]);
+        index.remove("payments");
+        assert_eq!(range(&index, "pay", None), vec!["pay", "payments/runbook"]);
+    }
+
+    #[test]
+    fn it_suggests_the_most_used_completions() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["pay", "payments", "payroll", "paystubs", "standup"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        let usage = Usage::from(HashMap::from([
+            ("payroll".to_string(), 3),
+            ("paystubs".to_string(), 3),
+            ("standup".to_string(), 10),
+        ]));
+        let suggest = |prefix, limit| {
+            index
+                .suggest(prefix, &usage, limit)
+                .into_iter()
+                .map(|(alias, _, used)| (alias.as_str(), used))
+                .collect::<Vec<(&str, u64)>>()
+        };
+        assert_eq!(
+            suggest("pay", 3),
+            vec![("payroll", 3), ("paystubs", 3), ("pay", 0)]
+        );
+        assert_eq!(suggest("stand", 3), vec![("standup", 10)]);
+        assert_eq!(suggest("wiki", 3), vec![]);
+    }
+
+    #[test]
+    fn it_suggests_the_most_used_completions_of_short_prefixes() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["pay", "payments", "payroll", "standup"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        let usage = Usage::from(HashMap::from([("payroll".to_string(), 3)]));
+        let suggest = |index: &RustlinkIndex, prefix| {
+            index
+                .suggest(prefix, &usage, 2)
+                .into_iter()
+                .map(|(alias, _, used)| (alias.clone(), used))
+                .collect::<Vec<(String, u64)>>()
+        };
+        let owned = |suggestions: &[(&str, u64)]| {
+            suggestions
+                .iter()
+                .map(|(alias, used)| (alias.to_string(), *used))
+                .collect::<Vec<(String, u64)>>()
+        };
+        assert_eq!(suggest(&index, "p"), owned(&[("payroll", 3), ("pay", 0)]));
+
+        // Usage is current, even while which links complete the prefix is
+        // remembered
+        usage.extend([("payments".to_string(), 5)]);
+        assert_eq!(
+            suggest(&index, "p"),
+            owned(&[("payments", 5), ("payroll", 3)])
+        );
+
+        // While links which were created or deleted since are taken into account
+        index.insert("paystubs".to_string(), Rustlink::default());
+        usage.extend([("paystubs".to_string(), 10)]);
+        assert_eq!(
+            suggest(&index, "p"),
+            owned(&[("paystubs", 10), ("payments", 5)])
+        );
+
+        index.remove("payments");
+        assert_eq!(
+            suggest(&index, "p"),
+            owned(&[("paystubs", 10), ("payroll", 3)])
+        );
+        assert_eq!(
+            suggest(&index, ""),
+            owned(&[("paystubs", 10), ("payroll", 3)])
+        );
+    }
+
+    #[test]
+    fn it_boosts_search_results_by_usage() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["payments-runbook", "payments-dashboard", "standup"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        let search = |usage: &Usage| {
+            index
+                .search("payments", usage, 10)
+                .into_iter()
+                .map(|(alias, _, _)| alias.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            search(&Usage::default()),
+            vec!["payments-dashboard", "payments-runbook"]
+        );
+        assert_eq!(
+            search(&HashMap::from([("payments-runbook".to_string(), 20)]).into()),
+            vec!["payments-runbook", "payments-dashboard"]
+        );
+    }
+
+    #[test]
+    fn it_suggests_closest_aliases_first() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["oncall", "oncall-payments", "standup", "payments"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        let closest: Vec<&str> = index
+            .closest("onclal", 5)
+            .into_iter()
+            .map(|(alias, _)| alias.as_str())
+            .collect();
+        assert_eq!(closest, vec!["oncall"]);
+    }
+
+    fn reference(alias: &str) -> Rustlink {
+        Rustlink {
+            url: format!("alias:{}", alias),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_lists_recently_modified_links_first() {
+        let mut index = RustlinkIndex::default();
+        let modified_at = |revision| Rustlink {
+            revision: Some(revision),
+            ..Default::default()
+        };
+
+        index.insert("pay".to_string(), modified_at(1));
+        index.insert("payments".to_string(), modified_at(2));
+        index.insert("standup".to_string(), modified_at(3));
+        index.insert("pay".to_string(), modified_at(4));
+        let modified = |index: &RustlinkIndex, prefix, after| {
+            index
+                .recently_modified(prefix, after)
+                .map(|(alias, _)| alias.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            modified(&index, "", None),
+            vec!["pay", "standup", "payments"]
+        );
+        assert_eq!(modified(&index, "pay", None), vec!["pay", "payments"]);
+        assert_eq!(modified(&index, "pay", Some((4, "pay"))), vec!["payments"]);
+
+        index.remove("payments");
+        assert_eq!(modified(&index, "pay", None), vec!["pay"]);
+    }
+
+    #[test]
+    fn it_follows_references() {
+        let mut index = RustlinkIndex::default();
+        index.insert("oncall".to_string(), reference("oncall-payments"));
+        index.insert(
+            "oncall-payments".to_string(),
+            reference("oncall-payments-q4"),
+        );
+        index.insert(
+            "oncall-payments-q4".to_string(),
+            Rustlink {
+                url: "https://oncall.example.com/payments".to_string(),
+                ..Default::default()
+            },
+        );
+        let (alias, rustlink) = index
+            .follow_references("oncall", &index["oncall"], &[])
+            .unwrap();
+        assert_eq!(alias, "oncall-payments-q4");
+        assert_eq!(rustlink.url, "https://oncall.example.com/payments");
+    }
+
+    #[test]
+    fn it_detects_reference_cycles() {
+        let mut index = RustlinkIndex::default();
+        index.insert("b".to_string(), reference("c"));
+        index.insert("c".to_string(), reference("a"));
+        // `a` doesn't exist yet, but would complete the cycle if written
+        assert_eq!(
+            index.follow_references("a", &reference("b"), &[]),
+            Err(ReferenceError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn it_detects_dangling_references() {
+        let index = RustlinkIndex::default();
+        assert_eq!(
+            index.follow_references("a", &reference("b"), &[]),
+            Err(ReferenceError::Dangling("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn it_checks_references_which_are_not_active_yet() {
+        let mut index = RustlinkIndex::default();
+        index.insert(
+            "oncall".to_string(),
+            Rustlink {
+                url: "https://oncall.example.com".to_string(),
+                schedule: vec![ScheduledTarget {
+                    url: "alias:pager".to_string(),
+                    valid_from: Some("2099-01-01T00:00:00Z".parse().unwrap()),
+                    valid_until: None,
+                }],
+                ..Default::default()
+            },
+        );
+        // Only a cycle once `oncall` switches over to `pager`
+        assert_eq!(
+            index.check_references("pager", &reference("oncall"), &[]),
+            Err(ReferenceError::Cycle(vec![
+                "pager".to_string(),
+                "oncall".to_string(),
+                "pager".to_string()
+            ]))
+        );
+        assert_eq!(
+            index.check_references(
+                "standup",
+                &Rustlink {
+                    url: "https://meet.example.com".to_string(),
+                    schedule: vec![ScheduledTarget {
+                        url: "alias:retro".to_string(),
+                        valid_from: None,
+                        valid_until: Some("2000-01-01T00:00:00Z".parse().unwrap()),
+                    }],
+                    ..Default::default()
+                },
+                &[]
+            ),
+            Err(ReferenceError::Dangling("retro".to_string()))
+        );
+    }
+
+    #[test]
+    fn it_limits_suggestions() {
+        let mut index = RustlinkIndex::default();
+
+        for alias in ["docs1", "docs2", "docs3"] {
+            index.insert(alias.to_string(), Rustlink::default());
+        }
+        assert_eq!(index.closest("docs", 2).len(), 2);
+    }
+
+    #[test]
+    fn it_retags_replaced_links() {
+        let mut index = RustlinkIndex::default();
+        let tagged = |tags: &[&str]| Rustlink {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+
+        index.insert("standup".to_string(), tagged(&["meetings"]));
+        index.insert("standup".to_string(), tagged(&["team"]));
+        index.insert("retro".to_string(), tagged(&["team"]));
+        assert_eq!(
+            index.tags().collect::<Vec<(&String, usize)>>(),
+            vec![(&"team".to_string(), 2)]
+        );
+
+        index.remove("retro");
+        assert_eq!(
+            index
+                .tagged("team")
+                .map(|(alias, _)| alias.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["standup"]
+        );
+    }
+}
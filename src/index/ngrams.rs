@@ -0,0 +1,95 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::RustlinkAlias;
+
+/// Characters of an alias considered when finding similar ones, so that
+/// arbitrarily long paths can't make lookups arbitrarily expensive
+const MAX_LOOKUP_LENGTH: usize = 64;
+
+/// The trigrams of an alias, padded so that its start and end count too (e.g.
+/// `  o`, ` on`, `onc`, ..., `ll ` for `oncall`)
+fn trigrams(alias: &str) -> BTreeSet<String> {
+    let padded: Vec<char> = "  "
+        .chars()
+        .chain(alias.chars().take(MAX_LOOKUP_LENGTH))
+        .chain(" ".chars())
+        .collect();
+
+    padded
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+/// The aliases containing each trigram, to find aliases similar to one which
+/// doesn't exist without comparing it against every alias
+#[derive(Clone, Debug, Default)]
+pub struct NgramIndex {
+    aliases: HashMap<String, BTreeSet<RustlinkAlias>>,
+}
+
+impl NgramIndex {
+    pub fn insert(&mut self, alias: &str) {
+        for trigram in trigrams(alias) {
+            self.aliases
+                .entry(trigram)
+                .or_default()
+                .insert(alias.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, alias: &str) {
+        for trigram in trigrams(alias) {
+            if let Some(aliases) = self.aliases.get_mut(&trigram) {
+                aliases.remove(alias);
+
+                if aliases.is_empty() {
+                    self.aliases.remove(&trigram);
+                }
+            }
+        }
+    }
+
+    /// The (at most `limit`) aliases sharing the most trigrams with `alias`,
+    /// most shared first (then in alias order)
+    pub fn candidates(&self, alias: &str, limit: usize) -> Vec<&RustlinkAlias> {
+        let mut shared: HashMap<&RustlinkAlias, usize> = HashMap::new();
+
+        for trigram in trigrams(alias) {
+            for candidate in self.aliases.get(&trigram).into_iter().flatten() {
+                *shared.entry(candidate).or_default() += 1;
+            }
+        }
+        let mut candidates: Vec<(&RustlinkAlias, usize)> = shared.into_iter().collect();
+        candidates
+            .sort_by(|(a_alias, a), (b_alias, b)| b.cmp(a).then_with(|| a_alias.cmp(b_alias)));
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|(candidate, _)| candidate)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_aliases_sharing_trigrams() {
+        let mut index = NgramIndex::default();
+
+        for alias in ["oncall", "oncall-payments", "standup"] {
+            index.insert(alias);
+        }
+        assert_eq!(
+            index.candidates("onclal", 10),
+            vec!["oncall", "oncall-payments"]
+        );
+        assert_eq!(index.candidates("onclal", 1), vec!["oncall"]);
+
+        index.remove("oncall");
+        assert_eq!(index.candidates("onclal", 10), vec!["oncall-payments"]);
+        assert!(index.candidates("wiki", 10).is_empty());
+    }
+}
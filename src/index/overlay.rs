@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use super::{Links, RustlinkIndex};
+use crate::{
+    rustlink::Rustlink,
+    util::{self, AliasNormalization},
+    RustlinkAlias,
+};
+
+/// The links of an index as changes which haven't been applied to it yet
+/// would leave them, without copying the index
+pub struct Overlay<'a> {
+    index: &'a RustlinkIndex,
+    /// Links changed so far, with `None` where one was removed
+    changes: HashMap<RustlinkAlias, Option<Rustlink>>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(index: &'a RustlinkIndex) -> Self {
+        Overlay {
+            index,
+            changes: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, alias: RustlinkAlias, rustlink: Rustlink) {
+        self.changes.insert(alias, Some(rustlink));
+    }
+
+    pub fn remove(&mut self, alias: &str) {
+        self.changes.insert(alias.to_string(), None);
+    }
+
+    /// The aliases of the links referring to `alias`, in order
+    pub fn referrers(
+        &self,
+        alias: &str,
+        normalizations: &[AliasNormalization],
+    ) -> Vec<&RustlinkAlias> {
+        let unchanged = self
+            .index
+            .iter()
+            .filter(|(referrer, _)| !self.changes.contains_key(*referrer));
+        let changed = self.changes.iter().filter_map(|(referrer, rustlink)| {
+            rustlink.as_ref().map(|rustlink| (referrer, rustlink))
+        });
+        let mut referrers: Vec<&RustlinkAlias> = unchanged
+            .chain(changed)
+            .filter(|(_, rustlink)| {
+                rustlink
+                    .references()
+                    .any(|target| util::canonicalize_alias(target, normalizations) == alias)
+            })
+            .map(|(referrer, _)| referrer)
+            .collect();
+
+        referrers.sort();
+        referrers
+    }
+}
+
+impl Links for Overlay<'_> {
+    fn link(&self, alias: &str) -> Option<&Rustlink> {
+        match self.changes.get(alias) {
+            Some(changed) => changed.as_ref(),
+            None => self.index.link(alias),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_shadows_the_links_of_the_index() {
+        let mut index = RustlinkIndex::default();
+        let link = |url: &str| Rustlink {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        index.insert("standup".to_string(), link("https://meet.example.com"));
+        index.insert("retro".to_string(), link("https://retro.example.com"));
+
+        let mut overlay = Overlay::new(&index);
+        overlay.remove("standup");
+        overlay.insert("retro".to_string(), link("https://board.example.com"));
+        overlay.insert("wiki".to_string(), link("https://wiki.example.com"));
+
+        assert_eq!(overlay.link("standup"), None);
+        assert_eq!(
+            overlay.link("retro").unwrap().url,
+            "https://board.example.com"
+        );
+        assert_eq!(
+            overlay.link("wiki").unwrap().url,
+            "https://wiki.example.com"
+        );
+        assert_eq!(overlay.link("oncall"), None);
+
+        index.insert("meetings".to_string(), link("alias:Retro"));
+        let mut overlay = Overlay::new(&index);
+        overlay.insert("board".to_string(), link("alias:retro"));
+        assert_eq!(
+            overlay.referrers("retro", &[AliasNormalization::Case]),
+            vec!["board", "meetings"]
+        );
+        overlay.remove("meetings");
+        assert_eq!(overlay.referrers("retro", &[]), vec!["board"]);
+        assert_eq!(
+            index.link("standup").unwrap().url,
+            "https://meet.example.com"
+        );
+    }
+}
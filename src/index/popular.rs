@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::RustlinkAlias;
+
+/// Prefixes (in characters) short enough for most links to complete them, so
+/// that their most used links are remembered rather than found every time
+const MAX_SHORT_PREFIX_LENGTH: usize = 2;
+
+/// Most used links remembered per short prefix, enough for any number of
+/// suggestions the API returns
+pub const MAX_POPULAR_LINKS: usize = 50;
+
+/// How long the most used links of a prefix are remembered, so that links
+/// which have become popular since are picked up
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Popular {
+    found_at: Instant,
+    aliases: Vec<RustlinkAlias>,
+}
+
+/// The most used links completing each short prefix, as of when they were
+/// last found. Only which links are candidates can be out of date, as they're
+/// ranked by their current usage whenever they're suggested.
+#[derive(Debug, Default)]
+pub struct PopularIndex {
+    prefixes: Mutex<HashMap<String, Popular>>,
+}
+
+/// Copies start out empty, as anything remembered is found again on demand
+impl Clone for PopularIndex {
+    fn clone(&self) -> Self {
+        PopularIndex::default()
+    }
+}
+
+impl PopularIndex {
+    /// Whether the most used links completing `prefix` are remembered
+    pub fn remembers(prefix: &str) -> bool {
+        prefix.chars().count() <= MAX_SHORT_PREFIX_LENGTH
+    }
+
+    /// Forget the links of every short prefix of `alias`, as it was created or
+    /// deleted
+    pub fn forget(&mut self, alias: &str) {
+        let prefixes = self
+            .prefixes
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        // Where each prefix of `alias` ends, from the empty one onwards
+        let ends = alias
+            .char_indices()
+            .map(|(end, _)| end)
+            .chain(std::iter::once(alias.len()));
+
+        for end in ends.take(MAX_SHORT_PREFIX_LENGTH + 1) {
+            prefixes.remove(&alias[..end]);
+        }
+    }
+
+    /// The most used links completing `prefix`, found with `find` unless
+    /// they're remembered from less than [`REFRESH_INTERVAL`] ago. Links are
+    /// found without holding the lock, so suggestions for other prefixes (or
+    /// the same one) don't wait on them.
+    pub fn get(
+        &self,
+        prefix: &str,
+        find: impl FnOnce() -> Vec<RustlinkAlias>,
+    ) -> Vec<RustlinkAlias> {
+        let remembered = self
+            .prefixes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(prefix)
+            .filter(|popular| popular.found_at.elapsed() < REFRESH_INTERVAL)
+            .map(|popular| popular.aliases.clone());
+
+        if let Some(aliases) = remembered {
+            return aliases;
+        }
+        let aliases = find();
+
+        self.prefixes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                prefix.to_string(),
+                Popular {
+                    found_at: Instant::now(),
+                    aliases: aliases.clone(),
+                },
+            );
+        aliases
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_remembers_links_until_ones_with_the_prefix_change() {
+        let mut index = PopularIndex::default();
+        let found = |aliases: &[&str]| aliases.iter().map(|alias| alias.to_string()).collect();
+
+        assert!(PopularIndex::remembers("pa"));
+        assert!(!PopularIndex::remembers("pay"));
+        assert_eq!(index.get("pa", || found(&["pay"])), vec!["pay"]);
+        assert_eq!(index.get("pa", || found(&["payments"])), vec!["pay"]);
+        assert_eq!(index.get("", || found(&["standup"])), vec!["standup"]);
+
+        // Links not starting with the prefix don't affect it
+        index.forget("standup");
+        assert_eq!(index.get("pa", || found(&["payments"])), vec!["pay"]);
+        assert_eq!(index.get("", || found(&["pay"])), vec!["pay"]);
+
+        index.forget("payments");
+        assert_eq!(index.get("pa", || found(&["payments"])), vec!["payments"]);
+        assert_eq!(index.get("", || found(&["payments"])), vec!["payments"]);
+    }
+
+    #[test]
+    fn it_finds_links_without_holding_the_lock() {
+        let index = PopularIndex::default();
+        let found = |aliases: &[&str]| aliases.iter().map(|alias| alias.to_string()).collect();
+
+        // Would deadlock if finding links held the lock
+        let aliases = index.get("pa", || index.get("st", || found(&["standup"])));
+        assert_eq!(aliases, vec!["standup"]);
+        assert_eq!(index.get("st", || found(&["status"])), vec!["standup"]);
+    }
+}
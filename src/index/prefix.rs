@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+/// A trie over the `/`-separated segments of every alias, used to find the
+/// longest alias prefixing a requested path in O(path depth)
+#[derive(Clone, Debug, Default)]
+pub struct PrefixIndex {
+    root: Node,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    terminal: bool,
+}
+
+impl Node {
+    /// Removes the alias made up of `segments` below this node, returning
+    /// whether this node is no longer needed and can be pruned by its parent
+    fn remove(&mut self, segments: &[&str]) -> bool {
+        match segments.split_first() {
+            Some((segment, rest)) => {
+                if let Some(child) = self.children.get_mut(*segment)
+                    && child.remove(rest)
+                {
+                    self.children.remove(*segment);
+                }
+            }
+            None => self.terminal = false,
+        }
+        !self.terminal && self.children.is_empty()
+    }
+}
+
+impl PrefixIndex {
+    pub fn insert(&mut self, alias: &str) {
+        let mut node = &mut self.root;
+
+        for segment in alias.split('/') {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node.terminal = true;
+    }
+
+    pub fn remove(&mut self, alias: &str) {
+        let segments: Vec<&str> = alias.split('/').collect();
+        self.root.remove(&segments);
+    }
+
+    /// Find the longest alias which is a prefix of `path` (on segment
+    /// boundaries), returning it along with the remainder of the path (without
+    /// its leading `/`)
+    pub fn longest_prefix<'a>(&self, path: &'a str) -> Option<(&'a str, &'a str)> {
+        let mut node = &self.root;
+        let mut longest: Option<usize> = None;
+        let mut end = 0;
+
+        for segment in path.split('/') {
+            match node.children.get(segment) {
+                Some(child) => {
+                    node = child;
+                    end += segment.len();
+
+                    if node.terminal {
+                        longest = Some(end);
+                    }
+                    end += 1;
+                }
+                None => break,
+            }
+        }
+        longest.map(|end| (&path[..end], path.get(end + 1..).unwrap_or("")))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_exact_match() {
+        let mut index = PrefixIndex::default();
+        index.insert("payments");
+        index.insert("payments/runbook");
+        assert_eq!(
+            index.longest_prefix("payments/runbook"),
+            Some(("payments/runbook", ""))
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_longest_prefix() {
+        let mut index = PrefixIndex::default();
+        index.insert("payments");
+        index.insert("payments/runbook");
+        assert_eq!(
+            index.longest_prefix("payments/unknown/page"),
+            Some(("payments", "unknown/page"))
+        );
+        assert_eq!(
+            index.longest_prefix("payments/runbook/2023"),
+            Some(("payments/runbook", "2023"))
+        );
+    }
+
+    #[test]
+    fn it_only_matches_whole_segments() {
+        let mut index = PrefixIndex::default();
+        index.insert("pay");
+        assert_eq!(index.longest_prefix("payments/runbook"), None);
+    }
+
+    #[test]
+    fn it_prunes_removed_aliases() {
+        let mut index = PrefixIndex::default();
+        index.insert("payments");
+        index.insert("payments/runbook");
+        index.remove("payments");
+        assert_eq!(index.longest_prefix("payments/unknown"), None);
+        assert_eq!(
+            index.longest_prefix("payments/runbook"),
+            Some(("payments/runbook", ""))
+        );
+        index.remove("payments/runbook");
+        assert!(index.root.children.is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, HashMap};
+
+use url::Url;
+
+use crate::{rustlink::Rustlink, RustlinkAlias};
+
+/// How much a token counts towards relevance, by where in a link it's found
+const ALIAS_WEIGHT: f64 = 3.0;
+const TAG_WEIGHT: f64 = 2.0;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+const URL_WEIGHT: f64 = 1.0;
+
+/// Query tokens which only prefix a link's token (e.g. `pay` for `payments`)
+/// count for this much of a whole match, so that results can be returned as
+/// the query is being typed
+const PREFIX_MATCH_FACTOR: f64 = 0.5;
+
+/// An inverted index from the tokens of each link's alias, tags, URLs, and
+/// description to the aliases of the links they're found in
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    /// Ordered so that tokens starting with a query token can be found
+    /// without scanning all of them
+    postings: BTreeMap<String, HashMap<RustlinkAlias, f64>>,
+    /// The tokens indexed per alias, to remove them when the link changes
+    documents: HashMap<RustlinkAlias, Vec<String>>,
+}
+
+/// Split text into lowercase alphanumeric tokens
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Tokens of a URL's host and path, leaving out its scheme and query (which
+/// are mostly noise, e.g. `https` or `utm_source`)
+fn tokenize_url(url: &str) -> Vec<String> {
+    match Url::parse(url) {
+        Ok(parsed) => tokenize(parsed.host_str().unwrap_or_default())
+            .chain(tokenize(parsed.path()))
+            .collect(),
+        // Templates may not parse until they're rendered
+        Err(_) => tokenize(url).collect(),
+    }
+}
+
+impl SearchIndex {
+    pub fn insert(&mut self, alias: &str, rustlink: &Rustlink) {
+        self.remove(alias);
+        let mut weights: HashMap<String, f64> = HashMap::new();
+        let mut add = |tokens: Vec<String>, weight: f64| {
+            for token in tokens {
+                let existing = weights.entry(token).or_default();
+                *existing = existing.max(weight);
+            }
+        };
+
+        add(tokenize(alias).collect(), ALIAS_WEIGHT);
+        if let Some(display_alias) = &rustlink.display_alias {
+            add(tokenize(display_alias).collect(), ALIAS_WEIGHT);
+        }
+        for tag in &rustlink.tags {
+            add(tokenize(tag).collect(), TAG_WEIGHT);
+        }
+        for url in rustlink.urls() {
+            add(tokenize_url(url), URL_WEIGHT);
+        }
+        if let Some(description) = &rustlink.description {
+            add(tokenize(description).collect(), DESCRIPTION_WEIGHT);
+        }
+
+        for (token, weight) in &weights {
+            self.postings
+                .entry(token.clone())
+                .or_default()
+                .insert(alias.to_string(), *weight);
+        }
+        self.documents
+            .insert(alias.to_string(), weights.into_keys().collect());
+    }
+
+    pub fn remove(&mut self, alias: &str) {
+        for token in self.documents.remove(alias).unwrap_or_default() {
+            if let Some(aliases) = self.postings.get_mut(&token) {
+                aliases.remove(alias);
+
+                if aliases.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// The relevance of every link matching all of the tokens in `query`,
+    /// where each query token matches the link's tokens it equals or prefixes
+    pub fn search(&self, query: &str) -> HashMap<&RustlinkAlias, f64> {
+        let mut scores: Option<HashMap<&RustlinkAlias, f64>> = None;
+
+        for query_token in tokenize(query) {
+            let mut token_scores: HashMap<&RustlinkAlias, f64> = HashMap::new();
+
+            for (token, aliases) in self
+                .postings
+                .range(query_token.clone()..)
+                .take_while(|(token, _)| token.starts_with(&query_token))
+            {
+                let factor = match *token == query_token {
+                    true => 1.0,
+                    false => PREFIX_MATCH_FACTOR,
+                };
+                for (alias, weight) in aliases {
+                    let score = token_scores.entry(alias).or_default();
+                    *score = score.max(weight * factor);
+                }
+            }
+
+            scores = Some(match scores {
+                None => token_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(alias, score)| {
+                        token_scores.get(alias).map(|other| (alias, score + other))
+                    })
+                    .collect(),
+            });
+        }
+        scores.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        index.insert(
+            "payments",
+            &Rustlink {
+                url: "https://wiki.example.com/teams/payments".to_string(),
+                description: Some("Payments team home page".to_string()),
+                ..Default::default()
+            },
+        );
+        index.insert(
+            "oncall",
+            &Rustlink {
+                url: "https://pager.example.com/schedules?team=payments".to_string(),
+                description: Some("Who's on call for payments".to_string()),
+                tags: vec!["incidents".to_string()],
+                ..Default::default()
+            },
+        );
+        index.insert(
+            "standup",
+            &Rustlink {
+                url: "https://meet.example.com/standup-{date}".to_string(),
+                ..Default::default()
+            },
+        );
+        index
+    }
+
+    fn search(index: &SearchIndex, query: &str) -> HashMap<String, f64> {
+        index
+            .search(query)
+            .into_iter()
+            .map(|(alias, score)| (alias.clone(), score))
+            .collect()
+    }
+
+    #[test]
+    fn it_tokenizes_on_non_alphanumerics() {
+        assert_eq!(
+            tokenize("On-call/Payments Q4").collect::<Vec<String>>(),
+            vec!["on", "call", "payments", "q4"]
+        );
+        assert_eq!(
+            tokenize_url("https://wiki.example.com/teams/payments?utm_source=go"),
+            vec!["wiki", "example", "com", "teams", "payments"]
+        );
+    }
+
+    #[test]
+    fn it_ranks_alias_matches_above_other_matches() {
+        let index = index();
+        let scores = search(&index, "payments");
+        assert_eq!(scores.len(), 2);
+        assert!(scores["payments"] > scores["oncall"]);
+    }
+
+    #[test]
+    fn it_matches_tags() {
+        let index = index();
+        let scores = search(&index, "incidents");
+        assert_eq!(scores["oncall"], TAG_WEIGHT);
+        assert_eq!(scores.len(), 1);
+    }
+
+    #[test]
+    fn it_requires_every_query_token_to_match() {
+        let index = index();
+        let scores = search(&index, "call payments");
+        assert_eq!(scores.into_keys().collect::<Vec<String>>(), vec!["oncall"]);
+        assert!(index.search("payments standup").is_empty());
+    }
+
+    #[test]
+    fn it_matches_query_tokens_prefixing_link_tokens() {
+        let index = index();
+        let scores = search(&index, "stand");
+        assert_eq!(scores["standup"], ALIAS_WEIGHT * PREFIX_MATCH_FACTOR);
+        assert_eq!(index.search("meet").len(), 1);
+    }
+
+    #[test]
+    fn it_forgets_removed_and_replaced_links() {
+        let mut index = index();
+        index.remove("payments");
+        assert_eq!(index.search("wiki").len(), 0);
+        index.insert(
+            "oncall",
+            &Rustlink {
+                url: "https://pager.example.com/schedules".to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(index.search("payments").is_empty());
+        assert!(index.postings.keys().all(|token| !token.contains("team")));
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::RustlinkAlias;
+
+/// The aliases of the links carrying each tag
+#[derive(Clone, Debug, Default)]
+pub struct TagIndex {
+    tagged: BTreeMap<String, BTreeSet<RustlinkAlias>>,
+}
+
+impl TagIndex {
+    pub fn insert(&mut self, alias: &str, tags: &[String]) {
+        for tag in tags {
+            self.tagged
+                .entry(tag.clone())
+                .or_default()
+                .insert(alias.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, alias: &str, tags: &[String]) {
+        for tag in tags {
+            if let Some(aliases) = self.tagged.get_mut(tag) {
+                aliases.remove(alias);
+
+                if aliases.is_empty() {
+                    self.tagged.remove(tag);
+                }
+            }
+        }
+    }
+
+    /// Every tag in order, along with how many links carry it
+    pub fn counts(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.tagged
+            .iter()
+            .map(|(tag, aliases)| (tag, aliases.len()))
+    }
+
+    /// The aliases of the links carrying `tag`, in order
+    pub fn aliases(&self, tag: &str) -> impl Iterator<Item = &RustlinkAlias> {
+        self.tagged.get(tag).into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_tags_and_forgets_untagged_ones() {
+        let mut index = TagIndex::default();
+        index.insert("standup", &["meetings".to_string(), "team".to_string()]);
+        index.insert("retro", &["meetings".to_string()]);
+
+        assert_eq!(
+            index.counts().collect::<Vec<(&String, usize)>>(),
+            vec![(&"meetings".to_string(), 2), (&"team".to_string(), 1)]
+        );
+        assert_eq!(
+            index.aliases("meetings").collect::<Vec<&RustlinkAlias>>(),
+            vec!["retro", "standup"]
+        );
+
+        index.remove("standup", &["meetings".to_string(), "team".to_string()]);
+        assert_eq!(index.counts().count(), 1);
+        assert_eq!(index.aliases("team").count(), 0);
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use crate::store::{Condition, Operation, Store};
+
+/// The key holding the current leader's claim, outside of every namespace
+/// links are stored in
+pub const LEADER_KEY: &str = "rustlinks-leader";
+
+/// A node's claim to be the leader, which lapses unless it's renewed
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Claim {
+    pub holder: String,
+}
+
+/// An id unique to this node, as nodes can share a hostname and configuration
+/// (e.g. replicas behind a load balancer)
+pub fn node_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "rustlinks".to_string());
+    format!("{}-{:016x}", host, rand::random::<u64>())
+}
+
+/// Leadership as seen by a single node. Claims lapse once they haven't been
+/// renewed for `ttl`, as measured by the node watching them rather than by the
+/// leader, so that nodes' clocks needn't agree.
+pub struct Leadership {
+    holder: String,
+    ttl: Duration,
+    /// The revision the current claim was last seen renewed at, and when
+    observed: Option<(i64, Instant)>,
+}
+
+impl Leadership {
+    pub fn new(holder: String, ttl: Duration) -> Self {
+        Leadership {
+            holder,
+            ttl,
+            observed: None,
+        }
+    }
+
+    /// Claim (or renew) leadership as of `now`, returning whether this node is
+    /// the leader. Leadership is only taken over once the current leader's
+    /// claim has lapsed, and claims are compared-and-swapped so only one node
+    /// can take it over.
+    pub async fn claim(&mut self, store: &dyn Store, now: Instant) -> Result<bool, etcd_rs::Error> {
+        let condition = match store.get(LEADER_KEY).await? {
+            Some(stored) => {
+                let current = serde_json::from_slice::<Claim>(&stored.value).ok();
+
+                // Unreadable claims are taken over, rather than blocking every node
+                if let Some(current) = current
+                    && current.holder != self.holder
+                {
+                    let renewed_at = match self.observed {
+                        Some((revision, seen_at)) if revision == stored.mod_revision => seen_at,
+                        _ => {
+                            self.observed = Some((stored.mod_revision, now));
+                            now
+                        }
+                    };
+
+                    if now.duration_since(renewed_at) < self.ttl {
+                        return Ok(false);
+                    }
+                }
+                Condition::ModRevision(LEADER_KEY.to_string(), stored.mod_revision)
+            }
+            None => Condition::Absent(LEADER_KEY.to_string()),
+        };
+        let claim = Claim {
+            holder: self.holder.clone(),
+        };
+        let value = serde_json::to_vec(&claim).unwrap_or_default();
+        let claimed = store
+            .txn(
+                vec![condition],
+                vec![Operation::Put(LEADER_KEY.to_string(), value)],
+            )
+            .await?;
+
+        Ok(claimed.is_some())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::store::memory::InMemoryStore;
+
+    #[actix_web::test]
+    async fn it_hands_over_leadership_once_claims_lapse() {
+        let store = InMemoryStore::default();
+        let ttl = Duration::from_secs(600);
+        let minutes = |minutes: u64| Instant::now() + Duration::from_secs(minutes * 60);
+        let mut a = Leadership::new("a".to_string(), ttl);
+        let mut b = Leadership::new("b".to_string(), ttl);
+
+        assert!(a.claim(&store, minutes(0)).await.unwrap());
+        assert!(!b.claim(&store, minutes(5)).await.unwrap());
+        assert!(a.claim(&store, minutes(6)).await.unwrap());
+        // `b` only saw the renewal at 7 minutes, so it counts from then
+        assert!(!b.claim(&store, minutes(7)).await.unwrap());
+        assert!(!b.claim(&store, minutes(16)).await.unwrap());
+        assert!(b.claim(&store, minutes(18)).await.unwrap());
+        assert!(!a.claim(&store, minutes(19)).await.unwrap());
+    }
+
+    #[test]
+    fn it_tells_nodes_apart() {
+        assert_ne!(node_id(), node_id());
+    }
+}
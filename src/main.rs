@@ -5,13 +5,23 @@
 
 pub mod api;
 pub mod cli;
+pub mod collection;
 pub mod errors;
+pub mod fallback;
+pub mod index;
+pub mod leader;
 pub mod oidc;
 pub mod redirect;
+pub mod reserved;
 pub mod rustlink;
+pub mod shortcode;
 pub mod state;
+pub mod store;
+pub mod template;
 pub mod tls;
+pub mod trash;
 pub mod ui;
+pub mod usage;
 pub mod util;
 pub mod worker;
 
@@ -68,17 +78,34 @@ async fn start(cli: cli::RustlinksOpts) -> Result<(), errors::RustlinksError> {
     let cli::Commands::Start {
         hostname,
         port,
+        public_url,
         data_dir,
         cert_file,
         key_file,
         oidc_providers,
         oauth_redirect_uri: oauth_redirect_endpoint,
         login_path,
+        alias_normalization,
+        fallback_url,
+        fallback_secret,
+        personal_links,
+        template_variable,
+        short_code_min_length,
+        short_code_alphabet,
+        trash_retention_days,
     }: cli::Commands = cli.command
     else {
         unreachable!();
     };
 
+    let short_codes = shortcode::ShortCodes {
+        min_length: short_code_min_length,
+        alphabet: short_code_alphabet.chars().collect(),
+    };
+    short_codes
+        .validate(&alias_normalization)
+        .map_err(RustlinksError::ParseError)?;
+
     let links_filepath = data_dir.join(LINK_FILENAME);
 
     match links_filepath.parent() {
@@ -105,11 +132,37 @@ async fn start(cli: cli::RustlinksOpts) -> Result<(), errors::RustlinksError> {
         }
     };
 
+    let url = match Url::parse(oauth_redirect_endpoint.as_str()) {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to parse OAuth redirect endpoint: {:?}", e);
+            return Err(RustlinksError::OAuthEndpointParseError(e));
+        }
+    };
+    let reserved_aliases =
+        reserved::ReservedAliases::new(&login_path, url.path(), &alias_normalization);
+    let etcd_client = Arc::new(etcd_client);
+    reserved_aliases
+        .report_conflicts(etcd_client.as_ref())
+        .await;
+
     let oidc_providers = oidc::provider::populate_provider_metadata(oidc_providers).await;
+    // Only a scheme and host (and port), as links are served from the root
+    let public_url = match Url::parse(&public_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.path() == "/" => {
+            public_url.trim_end_matches('/').to_string()
+        }
+        _ => {
+            return Err(RustlinksError::ParseError(format!(
+                "Invalid public URL (expected e.g. https://go.example.com): {}",
+                public_url
+            )));
+        }
+    };
 
     let state = web::Data::new(state::AppState {
         rustlinks: Arc::new(RwLock::new(Default::default())),
-        etcd_client: Arc::new(etcd_client),
+        store: etcd_client.clone(),
         revision: Arc::new(RwLock::new(0)),
         links_file: Arc::new(RwLock::new(links_file)),
         read_only: cli.global.read_only,
@@ -117,40 +170,88 @@ async fn start(cli: cli::RustlinksOpts) -> Result<(), errors::RustlinksError> {
         js_source: Arc::new(RwLock::new(read_to_string("./src/ui/dist/index.js")?)),
         oidc_providers: Arc::new(RwLock::new(oidc_providers)),
         login_path: login_path.clone(),
+        alias_normalization,
+        fallbacks: fallback_url,
+        fallback_secret,
+        personal_owners: personal_links,
+        personal_rustlinks: Arc::new(RwLock::new(Default::default())),
+        collections: Arc::new(RwLock::new(Default::default())),
+        template_variables: template_variable,
+        usage: Default::default(),
+        short_codes,
+        reserved: reserved_aliases,
+        public_url,
+        trash_retention: chrono::Duration::days(trash_retention_days.into()),
+        trash: Arc::new(RwLock::new(Default::default())),
     });
     let worker = Box::new(Worker {
         state: state.clone(),
-        cancel: Arc::new(Mutex::new(None)),
+        etcd_client,
+        cancel: Arc::new(Mutex::new(vec![])),
         sleep: Arc::new(Mutex::new(None)),
     });
-    let url = match Url::parse(oauth_redirect_endpoint.as_str()) {
-        Ok(u) => u,
-        Err(e) => {
-            eprintln!("Failed to parse OAuth redirect endpoint: {:?}", e);
-            return Err(RustlinksError::OAuthEndpointParseError(e));
-        }
-    };
 
     let server = HttpServer::new(move || {
         App::new()
             .app_data(state.clone())
             .service(
-                web::scope("/api/v1")
+                web::scope(reserved::API_PATH)
                     .service(web::scope("/health").service(api::v1::health::check))
+                    .service(api::v1::batch::batch_rustlinks)
                     .service(
                         // TODO: parse bearer auth middleware
                         web::scope("/links")
+                            .service(api::v1::links::get_personal_rustlinks)
+                            .service(api::v1::links::create_personal_rustlink)
+                            .service(api::v1::links::delete_personal_rustlink)
+                            .service(api::v1::links::get_rustlinks)
+                            .service(api::v1::links::get_rustlink)
                             .service(api::v1::links::create_rustlink)
-                            .service(api::v1::links::delete_rustlink)
-                            .service(api::v1::links::get_rustlinks),
+                            .service(api::v1::links::update_rustlink)
+                            .service(api::v1::links::rename_rustlink)
+                            .service(api::v1::links::create_short_rustlink)
+                            .service(api::v1::links::delete_rustlink),
+                    )
+                    .service(web::scope("/search").service(api::v1::search::search))
+                    .service(web::scope("/suggest").service(api::v1::suggest::suggest))
+                    .service(
+                        web::scope("/tags")
+                            .service(api::v1::tags::get_tags)
+                            .service(api::v1::tags::get_tagged_rustlinks),
+                    )
+                    .service(
+                        web::scope("/collections")
+                            .service(api::v1::collections::get_collections)
+                            .service(api::v1::collections::get_collection)
+                            .service(api::v1::collections::put_collection)
+                            .service(api::v1::collections::delete_collection),
                     )
-                    .service(web::scope("/oauth")), //TODO: re-work oauth functions
+                    .service(
+                        web::scope("/trash")
+                            .service(api::v1::trash::get_trash)
+                            .service(api::v1::trash::restore_rustlink),
+                    )
+                    .service(
+                        web::scope(api::v1::opensearch::OPENSEARCH_PATH)
+                            .service(api::v1::opensearch::opensearch_description)
+                            .service(api::v1::opensearch::opensearch_search)
+                            .service(api::v1::opensearch::opensearch_suggest),
+                    )
+                    .service(
+                        //TODO: re-work oauth functions
+                        web::scope("/oauth")
+                            .service(api::v1::oauth::session)
+                            .service(api::v1::oauth::logout),
+                    ),
             )
             .service(web::resource(url.path()).route(web::get().to(api::v1::oauth::callback)))
             .service(web::scope(login_path.as_str()).service(ui::route::index))
-            .service(Files::new("/_ui/styles", "./src/ui/dist/styles").show_files_listing())
-            .service(Files::new("/_ui/images", "./src/ui/dist/images").show_files_listing())
-            .service(Files::new("/_ui/scripts", "./src/ui/dist/scripts").show_files_listing())
+            .service(
+                web::scope(reserved::UI_PATH)
+                    .service(Files::new("/styles", "./src/ui/dist/styles").show_files_listing())
+                    .service(Files::new("/images", "./src/ui/dist/images").show_files_listing())
+                    .service(Files::new("/scripts", "./src/ui/dist/scripts").show_files_listing()),
+            )
             .service(web::scope("/").service(ui::route::index))
             .service(redirect::redirect)
             .wrap(RequestMetrics::default())
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use actix_web::{
+    dev::Payload, error::ErrorUnauthorized, http::header::AUTHORIZATION, web, FromRequest,
+    HttpRequest,
+};
+use futures::future::LocalBoxFuture;
+use openidconnect::{
+    core::{
+        CoreGenderClaim, CoreIdTokenVerifier, CoreJsonWebKeyType,
+        CoreJweContentEncryptionAlgorithm, CoreJwsSigningAlgorithm,
+    },
+    AdditionalClaims, ClientId, IdToken, Nonce,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+/// Cookie holding the user's ID token, for navigating to links, which can't
+/// set an `Authorization` header
+pub const ID_TOKEN_COOKIE: &str = "rustlinks_id_token";
+
+/// The (non-standard, but widely supported) `groups` claim
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupsClaim {
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl AdditionalClaims for GroupsClaim {}
+
+pub(crate) type GroupsIdToken = IdToken<
+    GroupsClaim,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+    CoreJsonWebKeyType,
+>;
+
+/// The user a request was made by, as verified by one of the configured OIDC
+/// providers
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Identity {
+    pub issuer: String,
+    pub subject: String,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+}
+
+impl Identity {
+    /// The key personal links are stored under, the user's email where the
+    /// provider shares it, since that's what they'll recognise when configuring
+    /// nodes which sync their links
+    pub fn owner(&self) -> String {
+        self.email.clone().unwrap_or_else(|| self.subject.clone())
+    }
+}
+
+/// Extracts the identity of the user making a request, responding with 401 if
+/// they can't be identified
+impl FromRequest for Identity {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let data = req
+                .app_data::<web::Data<AppState>>()
+                .ok_or_else(|| ErrorUnauthorized("Unauthorized"))?;
+
+            identify(&req, data)
+                .await
+                .ok_or_else(|| ErrorUnauthorized("Unauthorized"))
+        })
+    }
+}
+
+/// The ID token `req` passes as a bearer token, if any
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
+
+/// Identify the user making `req`, from an ID token passed as a bearer token.
+/// Returns `None` if there's no token, or no configured provider can verify it.
+///
+/// Cookies aren't accepted here, as browsers also send them with requests
+/// other sites make (e.g. submitting a form to the API).
+pub async fn identify(req: &HttpRequest, data: &AppState) -> Option<Identity> {
+    verify(bearer_token(req)?, data).await
+}
+
+/// Identify the user following a link, from an ID token passed either as a
+/// bearer token or in the [`ID_TOKEN_COOKIE`] cookie, which is all a browser
+/// navigating to a link sends. Only for resolving links, which doesn't change
+/// anything.
+pub async fn identify_visitor(req: &HttpRequest, data: &AppState) -> Option<Identity> {
+    let token = match bearer_token(req) {
+        Some(token) => token.to_string(),
+        None => req.cookie(ID_TOKEN_COOKIE)?.value().to_string(),
+    };
+    verify(&token, data).await
+}
+
+/// Verify `token` with whichever configured provider issued it
+pub async fn verify(token: &str, data: &AppState) -> Option<Identity> {
+    let id_token = GroupsIdToken::from_str(token).ok()?;
+    let providers = data.oidc_providers.read().await;
+
+    providers.iter().find_map(|provider| {
+        let metadata = provider.provider_metadata.as_ref()?;
+        let verifier = CoreIdTokenVerifier::new_public_client(
+            ClientId::new(provider.client_id.clone()),
+            metadata.issuer().clone(),
+            metadata.jwks().clone(),
+        );
+        // Tokens are obtained by the UI, which is responsible for checking nonces
+        let claims = id_token
+            .claims(&verifier, |_: Option<&Nonce>| Ok(()))
+            .ok()?;
+
+        Some(Identity {
+            issuer: claims.issuer().to_string(),
+            subject: claims.subject().to_string(),
+            username: claims.preferred_username().map(|u| u.to_string()),
+            email: claims.email().map(|e| e.to_string()),
+            groups: claims.additional_claims().groups.clone(),
+        })
+    })
+}
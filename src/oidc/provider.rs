@@ -100,6 +100,80 @@ pub async fn populate_provider_metadata(providers: Vec<OIDCProvider>) -> Vec<OID
     .await
 }
 
+/// Signs the ID tokens of the provider tests configure
+#[cfg(test)]
+const TEST_SIGNING_KEY: &str = include_str!("test_signing_key.pem");
+
+#[cfg(test)]
+impl OIDCProvider {
+    /// A provider whose tokens tests can issue with
+    /// [`OIDCProvider::id_token_for_tests`]
+    pub fn for_tests() -> Self {
+        use openidconnect::{
+            core::{
+                CoreJsonWebKeySet, CoreJwsSigningAlgorithm, CoreResponseType,
+                CoreRsaPrivateSigningKey, CoreSubjectIdentifierType,
+            },
+            AuthUrl, EmptyAdditionalProviderMetadata, IssuerUrl, JsonWebKeySetUrl,
+            PrivateSigningKey, ResponseTypes,
+        };
+
+        let key = CoreRsaPrivateSigningKey::from_pem(TEST_SIGNING_KEY, None).unwrap();
+        let metadata = CoreProviderMetadata::new(
+            IssuerUrl::new("https://accounts.example.com".to_string()).unwrap(),
+            AuthUrl::new("https://accounts.example.com/authorize".to_string()).unwrap(),
+            JsonWebKeySetUrl::new("https://accounts.example.com/jwks".to_string()).unwrap(),
+            vec![ResponseTypes::new(vec![CoreResponseType::IdToken])],
+            vec![CoreSubjectIdentifierType::Public],
+            vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+            EmptyAdditionalProviderMetadata {},
+        )
+        .set_jwks(CoreJsonWebKeySet::new(vec![key.as_verification_key()]));
+
+        OIDCProvider {
+            client_id: "rustlinks".to_string(),
+            provider_url: "https://accounts.example.com".to_string(),
+            provider_name: ProviderName::Unknown,
+            provider_metadata: Some(metadata),
+        }
+    }
+
+    /// An ID token the provider from [`OIDCProvider::for_tests`] issued to
+    /// `email`, a member of `groups`
+    pub fn id_token_for_tests(email: &str, groups: &[&str]) -> String {
+        use openidconnect::{
+            core::{CoreJwsSigningAlgorithm, CoreRsaPrivateSigningKey},
+            Audience, EndUserEmail, IdTokenClaims, IssuerUrl, StandardClaims, SubjectIdentifier,
+        };
+
+        use super::identity::{GroupsClaim, GroupsIdToken};
+
+        let key = CoreRsaPrivateSigningKey::from_pem(TEST_SIGNING_KEY, None).unwrap();
+        let now = chrono::Utc::now();
+        let claims = IdTokenClaims::new(
+            IssuerUrl::new("https://accounts.example.com".to_string()).unwrap(),
+            vec![Audience::new("rustlinks".to_string())],
+            now + chrono::Duration::hours(1),
+            now,
+            StandardClaims::new(SubjectIdentifier::new(email.to_string()))
+                .set_email(Some(EndUserEmail::new(email.to_string()))),
+            GroupsClaim {
+                groups: groups.iter().map(|group| group.to_string()).collect(),
+            },
+        );
+
+        GroupsIdToken::new(
+            claims,
+            &key,
+            CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+            None,
+            None,
+        )
+        .unwrap()
+        .to_string()
+    }
+}
+
 #[cfg(test)]
 mod unit_tests {
     #[test]
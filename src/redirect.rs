@@ -1,61 +1,559 @@
 use std::collections::HashMap;
 
-use actix_web::{get, web, Either, HttpResponse};
+use actix_web::{
+    cookie::{time::Duration, Cookie},
+    get,
+    http::{
+        header::{CacheControl, CacheDirective, LOCATION},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse, ResponseError,
+};
+use chrono::Utc;
 use opentelemetry::{
     global,
     trace::{get_active_span, Tracer},
 };
+use rand::Rng;
+use serde_json::json;
+use url::{form_urlencoded, Url};
 use urlencoding::encode;
 
-use crate::state;
+use crate::{
+    errors::{ReferenceError, TemplateError},
+    fallback,
+    index::{Links, RustlinkIndex},
+    oidc::identity::identify_visitor,
+    rustlink::{RedirectMode, Rustlink, Selection},
+    state, template, ui,
+    util::{self, AliasNormalization},
+    RustlinkAlias,
+};
+
+/// Maximum number of similar aliases suggested when an alias doesn't exist
+const SUGGESTION_LIMIT: usize = 5;
+
+/// Seconds an interstitial page is shown before redirecting
+const INTERSTITIAL_DELAY_SECS: u32 = 1;
+
+/// Query parameter selecting how a bundle is returned, `json` for a list of
+/// its URLs rather than a page
+const FORMAT_PARAM: &str = "format";
+
+/// Query parameter which previews a link rather than following it
+const PREVIEW_PARAM: &str = "preview";
+
+/// Suffix of aliases which previews them rather than following them
+const PREVIEW_SUFFIX: char = '+';
+
+/// Days a client keeps being sent to the same weighted target
+const STICKY_TARGET_DAYS: i64 = 30;
 
 #[get("/{alias:.*}")]
 pub async fn redirect(
+    req: HttpRequest,
     state: web::Data<state::AppState>,
     path: web::Path<String>,
-) -> Either<web::Redirect, HttpResponse> {
+) -> HttpResponse {
     let tracer = global::tracer("redirect");
     tracer
         .in_span("render-url-template-and-redirect", async move |_| {
             let full = path.into_inner();
             let mut split = full.split(" ");
-            let alias = split.next().unwrap();
+            let path = split.next().unwrap();
             let params = split.remainder();
-            let rustlinks = state.rustlinks.read().await;
-
-            get_active_span(|span| match rustlinks.get(alias) {
-                Some(rustlink) => {
-                    let url = render_url_template(&rustlink.url, params.clone());
-                    // Increment counter for this alias
-                    let meter = global::meter("");
-                    let builder = meter.u64_counter("rustlinks.redirects");
-                    let counter = builder.init();
-                    counter.add(
-                        1,
-                        [opentelemetry::KeyValue::new(
-                            "rustlinks.alias",
-                            alias.to_string(),
-                        )]
-                        .as_ref(),
-                    );
-                    // Attach alias metadata to span
-                    span.set_attribute(opentelemetry::KeyValue::new(
-                        "rustlinks.alias",
-                        alias.to_string(),
-                    ));
-                    span.set_attribute(opentelemetry::KeyValue::new("rustlinks.url", url.clone()));
-                    span.set_attribute(opentelemetry::KeyValue::new(
-                        "rustlinks.params",
-                        params.unwrap_or("").to_string(),
-                    ));
-                    Either::Left(web::Redirect::to(url).permanent())
+            // `go/alias+` (or `?preview`) shows where a link goes rather than
+            // following it, unless the `+` is part of an alias (e.g. `c++`)
+            let mut preview = form_urlencoded::parse(req.query_string().as_bytes())
+                .any(|(key, _)| key == PREVIEW_PARAM);
+            let mut path = path;
+
+            if let Some(stripped) = path.strip_suffix(PREVIEW_SUFFIX) {
+                let canonical = util::canonicalize_alias(path, &state.alias_normalization);
+
+                if !state.rustlinks.read().await.contains_key(&canonical) {
+                    path = stripped;
+                    preview = true;
                 }
-                None => Either::Right(HttpResponse::NotFound().finish()),
-            })
+            }
+            // Personal links shadow shared ones for the user they belong to,
+            // with the shared alias used when there's no personal one
+            let path = match util::strip_personal_prefix(path) {
+                Some(personal_path) => {
+                    if let Some(identity) = identify_visitor(&req, &state).await
+                        && let Ok(personal) = state.personal_rustlinks(&identity.owner()).await
+                    {
+                        match find(&personal, personal_path, &state.alias_normalization) {
+                            Ok(Some(found)) => {
+                                return resolve(
+                                    &req,
+                                    &state,
+                                    found,
+                                    personal_path,
+                                    params,
+                                    true,
+                                    preview,
+                                )
+                                .await;
+                            }
+                            Ok(None) => {}
+                            Err(e) => return e.error_response(),
+                        }
+                    }
+                    personal_path
+                }
+                None => path,
+            };
+            let canonical = util::canonicalize_alias(path, &state.alias_normalization);
+            // The links are only locked while the link is found, as following
+            // it can wait on identity providers and rendering
+            let found = find(
+                &*state.rustlinks.read().await,
+                path,
+                &state.alias_normalization,
+            );
+
+            match found {
+                Ok(Some(found)) => {
+                    return resolve(&req, &state, found, path, params, false, preview).await;
+                }
+                Ok(None) => {}
+                Err(e) => return e.error_response(),
+            }
+
+            // Upstream servers asking us to resolve an alias handle falling back
+            // themselves
+            let secret = state.fallback_secret.as_deref();
+
+            if !fallback::skips_fallbacks(req.headers(), secret) {
+                // Fallbacks are asked for the alias as it would've been looked
+                // up, without the preview suffix or personal prefix
+                let requested = match params {
+                    Some(params) => format!("{} {}", canonical, params),
+                    None => canonical.clone(),
+                };
+
+                for fallback in state.fallbacks.iter() {
+                    if let Some(url) = fallback.resolve(&requested, secret).await {
+                        // Counted separately, to find aliases worth creating
+                        count_redirect(&canonical, true);
+                        get_active_span(|span| {
+                            span.set_attribute(opentelemetry::KeyValue::new(
+                                "rustlinks.alias",
+                                canonical.clone(),
+                            ));
+                            span.set_attribute(opentelemetry::KeyValue::new(
+                                "rustlinks.url",
+                                url.clone(),
+                            ));
+                            span.set_attribute(opentelemetry::KeyValue::new(
+                                "rustlinks.fallback",
+                                true,
+                            ));
+                        });
+                        return redirect_response(&url, RedirectMode::Found);
+                    }
+                }
+            }
+            let suggestions = state
+                .rustlinks
+                .read()
+                .await
+                .closest(&canonical, SUGGESTION_LIMIT)
+                .into_iter()
+                .map(|(alias, rustlink)| ui::route::Suggestion {
+                    alias: rustlink.display_alias.clone().unwrap_or(alias.clone()),
+                    url: rustlink.url.clone(),
+                })
+                .collect();
+
+            // Recently deleted links say so (and by whom), rather than
+            // leaving people wondering where they went
+            let deleted = state.trash.read().await.get(&canonical).cloned();
+
+            ui::route::not_found(&req, &state, path, suggestions, deleted.as_ref()).await
         })
         .await
 }
 
+/// A link found for a path, owned so that the links it was found among
+/// needn't stay locked while it's followed
+struct Found {
+    /// The alias matched
+    alias: RustlinkAlias,
+    /// The alias references from `alias` ended at, whose link this is
+    target: RustlinkAlias,
+    rustlink: Rustlink,
+}
+
+/// Find the link `path` resolves to among `rustlinks`, falling back to the
+/// longest alias prefixing the path when there's no exact match (e.g.
+/// `payments/unknown` resolves to `payments`). References to other aliases are
+/// followed here, rather than sending the client through each alias in the
+/// chain.
+fn find(
+    rustlinks: &RustlinkIndex,
+    path: &str,
+    normalizations: &[AliasNormalization],
+) -> Result<Option<Found>, ReferenceError> {
+    let canonical = util::canonicalize_alias(path, normalizations);
+    let Some((alias, matched, _)) = rustlinks.longest_prefix(&canonical) else {
+        return Ok(None);
+    };
+
+    match rustlinks.follow_references(alias, matched, normalizations) {
+        Ok((target, rustlink)) => Ok(Some(Found {
+            alias: alias.to_string(),
+            target,
+            rustlink: rustlink.clone(),
+        })),
+        // A link to an alias which doesn't exist (any more) goes nowhere, so
+        // it's treated like a miss rather than an error
+        Err(ReferenceError::Dangling(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Follow the link `path` was found to resolve to. Personal links are
+/// labelled as such in metrics and traces.
+async fn resolve(
+    req: &HttpRequest,
+    state: &state::AppState,
+    found: Found,
+    path: &str,
+    params: Option<&str>,
+    personal: bool,
+    preview: bool,
+) -> HttpResponse {
+    let Found {
+        alias,
+        target,
+        rustlink,
+    } = found;
+    let label = |alias: &str| match personal {
+        true => format!("{}{}", util::PERSONAL_PREFIXES[0], alias),
+        false => alias.to_string(),
+    };
+    let counted = label(&alias);
+    // Take the remainder from the original path to preserve its
+    // spelling, canonicalization keeps the number of segments
+    let depth = alias.split('/').count();
+    let remainder = path.splitn(depth + 1, '/').nth(depth).unwrap_or("");
+    // Whatever remains of the path is either appended to the
+    // destination, or passed along as leading parameters
+    let params = match rustlink.append_path || remainder.is_empty() {
+        true => params.map(str::to_string),
+        false => Some(
+            remainder
+                .split('/')
+                .chain(params)
+                .collect::<Vec<&str>>()
+                .join(" "),
+        ),
+    };
+    // Only verify the user's identity for links which depend on it
+    let identity = match rustlink.has_group_rules() || rustlink.urls().any(template::uses_user) {
+        true => identify_visitor(req, state).await,
+        false => None,
+    };
+    let groups = identity
+        .as_ref()
+        .map(|identity| identity.groups.clone())
+        .unwrap_or_default();
+    let cookie_name = target_cookie_name(&label(&target));
+    let sticky = req
+        .cookie(&cookie_name)
+        .and_then(|cookie| cookie.value().parse().ok());
+    let now = Utc::now();
+    let selection = rustlink.select(
+        now,
+        |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        },
+        &groups,
+        sticky,
+        |total| rand::thread_rng().gen_range(0..total),
+    );
+    // Bundles render each of their URLs, in place of a single destination
+    let selected: Vec<&str> = match rustlink.bundle.is_empty() {
+        true => vec![rustlink.selected_url(selection)],
+        false => rustlink.bundle.iter().map(String::as_str).collect(),
+    };
+
+    // Refused rather than rendering the user's claims as blanks. Users aren't
+    // sent to the login page, which doesn't set the cookie identifying them yet.
+    if identity.is_none() && selected.iter().any(|url| template::uses_user(url)) {
+        return TemplateError::Unidentified.error_response();
+    }
+    let format = form_urlencoded::parse(req.query_string().as_bytes())
+        .find(|(key, _)| key == FORMAT_PARAM)
+        .map(|(_, value)| value.into_owned());
+    // Parameters meant for us aren't forwarded to destinations
+    let ours = |key: &str| {
+        key == PREVIEW_PARAM || (key == FORMAT_PARAM && (preview || !rustlink.bundle.is_empty()))
+    };
+    let query = match form_urlencoded::parse(req.query_string().as_bytes())
+        .any(|(key, _)| ours(&key))
+    {
+        false => req.query_string().to_string(),
+        true => form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(
+                form_urlencoded::parse(req.query_string().as_bytes()).filter(|(key, _)| !ours(key)),
+            )
+            .finish(),
+    };
+    let urls = {
+        let variables = template::Variables {
+            user: identity.as_ref(),
+            now,
+            // Taken from configuration rather than the request, whose `Host`
+            // header is up to the client
+            host: state
+                .public_url
+                .split_once("://")
+                .map_or(state.public_url.as_str(), |(_, host)| host),
+            config: &state.template_variables,
+        };
+        let render = |selected: &str| -> Result<String, TemplateError> {
+            let selected = template::substitute(selected, &variables)?;
+            let mut url = render_url_template(&selected, params.as_deref());
+
+            if rustlink.append_path {
+                url = append_path_suffix(&url, remainder);
+            }
+            if rustlink.forward_query {
+                url = merge_query_string(&url, &query);
+            }
+            // Also checked when links are written, but parameters are only
+            // known now
+            template::check_scheme(&url)?;
+            Ok(url)
+        };
+
+        match selected
+            .into_iter()
+            .map(render)
+            .collect::<Result<Vec<String>, _>>()
+        {
+            Ok(urls) => urls,
+            Err(e) => return e.error_response(),
+        }
+    };
+    // Attach alias metadata to span
+    get_active_span(|span| {
+        span.set_attribute(opentelemetry::KeyValue::new(
+            "rustlinks.alias",
+            counted.clone(),
+        ));
+        if target != alias {
+            span.set_attribute(opentelemetry::KeyValue::new(
+                "rustlinks.target_alias",
+                target.clone(),
+            ));
+        }
+        span.set_attribute(opentelemetry::KeyValue::new(
+            "rustlinks.url",
+            urls.join(" "),
+        ));
+        span.set_attribute(opentelemetry::KeyValue::new(
+            "rustlinks.target",
+            selection.to_string(),
+        ));
+        span.set_attribute(opentelemetry::KeyValue::new(
+            "rustlinks.params",
+            params.unwrap_or_default(),
+        ));
+        span.set_attribute(opentelemetry::KeyValue::new("rustlinks.preview", preview));
+    });
+
+    if preview {
+        // Usage is only tracked for shared links
+        let usage = match personal {
+            true => None,
+            false => Some(state.usage.get(&alias)),
+        };
+        let preview = ui::route::Preview {
+            alias: counted,
+            target_alias: (target != alias).then(|| label(&target)),
+            urls,
+            description: rustlink.description.clone(),
+            owners: rustlink.owners.clone(),
+            tags: rustlink.tags.clone(),
+            created_at: rustlink.created_at,
+            created_by: rustlink.created_by.clone(),
+            updated_at: rustlink.updated_at,
+            updated_by: rustlink.updated_by.clone(),
+            archived: rustlink.archived,
+            deprecated: rustlink.deprecated,
+            revision: rustlink.revision,
+            usage,
+            redirect: rustlink.redirect,
+        };
+        return match format.as_deref() {
+            Some("json") => HttpResponse::Ok()
+                .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+                .json(preview),
+            _ => ui::route::preview(req, state, &preview).await,
+        };
+    }
+    // Increment counter for this alias
+    count_redirect(&counted, false);
+
+    if !personal {
+        state.usage.record(&alias);
+    }
+
+    if !rustlink.bundle.is_empty() {
+        return match format.as_deref() {
+            Some("json") => HttpResponse::Ok()
+                .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+                .json(json!({ "alias": counted, "urls": urls })),
+            _ => ui::route::bundle(req, state, &counted, &urls).await,
+        };
+    }
+    let mut response = redirect_response(&urls[0], rustlink.redirect);
+
+    // Keep clients on the same side of a weighted split
+    if let Selection::Target(index) = selection
+        && rustlink.weight(index) > 0
+        && sticky != Some(index)
+    {
+        let cookie = Cookie::build(cookie_name, index.to_string())
+            .path("/")
+            .max_age(Duration::days(STICKY_TARGET_DAYS))
+            .http_only(true)
+            .finish();
+        let _ = response.add_cookie(&cookie);
+    }
+    response
+}
+
+/// Name of the cookie remembering which weighted target of `alias` a client
+/// was sent to
+fn target_cookie_name(alias: &str) -> String {
+    format!("rustlinks_target_{}", encode(alias))
+}
+
+/// Send the client to `url` as configured by `mode`. Responses are never
+/// cached, so that edits to a link take effect immediately.
+pub fn redirect_response(url: &str, mode: RedirectMode) -> HttpResponse {
+    let status = match mode {
+        RedirectMode::Found => StatusCode::FOUND,
+        RedirectMode::MovedPermanently => StatusCode::MOVED_PERMANENTLY,
+        RedirectMode::TemporaryRedirect => StatusCode::TEMPORARY_REDIRECT,
+        RedirectMode::PermanentRedirect => StatusCode::PERMANENT_REDIRECT,
+        RedirectMode::Interstitial => StatusCode::OK,
+    };
+    let mut response = HttpResponse::build(status);
+    response.insert_header(CacheControl(vec![CacheDirective::NoStore]));
+
+    match mode {
+        RedirectMode::Interstitial => response
+            .content_type("text/html; charset=utf-8")
+            .body(interstitial(url)),
+        _ => response.insert_header((LOCATION, url)).finish(),
+    }
+}
+
+/// A minimal page showing where the client is about to be sent, before a
+/// meta refresh sends it there
+fn interstitial(url: &str) -> String {
+    let escaped = url
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;");
+
+    format!(
+        r##"<!doctype html>
+<html lang="en">
+<head>
+   <meta charset="UTF-8">
+   <meta http-equiv="refresh" content="{INTERSTITIAL_DELAY_SECS}; url={escaped}">
+   <title>Redirecting...</title>
+</head>
+<body>
+   <p>Redirecting to <a href="{escaped}">{escaped}</a></p>
+</body>
+</html>
+"##
+    )
+}
+
+/// Increment the redirect counter for `alias`, distinguishing redirects which
+/// were only resolved by a fallback
+fn count_redirect(alias: &str, fallback: bool) {
+    let meter = global::meter("");
+    let builder = meter.u64_counter("rustlinks.redirects");
+    let counter = builder.init();
+    counter.add(
+        1,
+        [
+            opentelemetry::KeyValue::new("rustlinks.alias", alias.to_string()),
+            opentelemetry::KeyValue::new("rustlinks.fallback", fallback),
+        ]
+        .as_ref(),
+    );
+}
+
+/// Append the path following an alias to the destination URL, keeping any
+/// query string or fragment the destination already has.
+pub fn append_path_suffix(url: &str, suffix: &str) -> String {
+    let suffix = suffix.trim_start_matches('/');
+
+    if suffix.is_empty() {
+        return url.to_string();
+    }
+
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            let path = format!("{}/{}", parsed.path().trim_end_matches('/'), suffix);
+            parsed.set_path(&path);
+            parsed.to_string()
+        }
+        Err(_) => format!("{}/{}", url.trim_end_matches('/'), suffix),
+    }
+}
+
+/// Merge the query string of the incoming request into the destination URL.
+/// Parameters present in both are taken from the incoming request, everything
+/// else in the destination is left untouched (including its encoding).
+pub fn merge_query_string(url: &str, query: &str) -> String {
+    if query.is_empty() {
+        return url.to_string();
+    }
+
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            let incoming_keys: Vec<String> = form_urlencoded::parse(query.as_bytes())
+                .map(|(key, _)| key.into_owned())
+                .collect();
+            let mut merged: Vec<&str> = parsed
+                .query()
+                .unwrap_or("")
+                .split('&')
+                .filter(|pair| {
+                    form_urlencoded::parse(pair.as_bytes())
+                        .next()
+                        .map_or(false, |(key, _)| !incoming_keys.contains(&key.into_owned()))
+                })
+                .collect();
+            merged.push(query);
+            let merged = merged.join("&");
+
+            parsed.set_query(Some(&merged));
+            parsed.to_string()
+        }
+        Err(_) => {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{url}{separator}{query}")
+        }
+    }
+}
+
 /// Take any params we received, and template them into the URL.
 /// Assumes that the params we receive are % decoded.
 pub fn render_url_template(template: &str, params: Option<&str>) -> String {
@@ -267,6 +765,44 @@ mod unit_tests {
         let templated = render_url_template(url, params);
         assert_eq!(templated, "https://google.com");
     }
+
+    #[test]
+    fn it_appends_path_suffix() {
+        let url = "https://docs.example.com/wiki/";
+        let appended = append_path_suffix(url, "guides/setup");
+        assert_eq!(appended, "https://docs.example.com/wiki/guides/setup");
+    }
+
+    #[test]
+    fn it_appends_path_suffix_before_query_and_fragment() {
+        let url = "https://docs.example.com/wiki?lang=en#top";
+        let appended = append_path_suffix(url, "guides/setup");
+        assert_eq!(
+            appended,
+            "https://docs.example.com/wiki/guides/setup?lang=en#top"
+        );
+    }
+
+    #[test]
+    fn it_merges_query_string() {
+        let url = "https://google.com/search?q=rust%20is%20cool";
+        let merged = merge_query_string(url, "hl=en");
+        assert_eq!(merged, "https://google.com/search?q=rust%20is%20cool&hl=en");
+    }
+
+    #[test]
+    fn it_merges_query_string_preferring_incoming_params() {
+        let url = "https://google.com/search?q=rust&hl=fr#results";
+        let merged = merge_query_string(url, "hl=en");
+        assert_eq!(merged, "https://google.com/search?q=rust&hl=en#results");
+    }
+
+    #[test]
+    fn it_escapes_urls_in_interstitial_pages() {
+        let page = interstitial("https://example.com/?a=1&b=\"><script>");
+        assert!(page.contains("url=https://example.com/?a=1&amp;b=&quot;&gt;&lt;script&gt;"));
+        assert!(!page.contains("<script>"));
+    }
 }
 
 #[cfg(test)]
@@ -274,39 +810,34 @@ mod integration_tests {
     use std::{collections::HashMap, sync::Arc};
 
     use actix_web::{test, App};
-    use etcd_rs::{Client, ClientConfig, Endpoint};
     use tokio::sync::RwLock;
 
     use super::*;
-    use crate::{rustlink::Rustlink, state::AppState, RustlinkAlias};
+    use crate::{
+        fallback::Fallback,
+        oidc::{identity::ID_TOKEN_COOKIE, provider::OIDCProvider},
+        rustlink::{Rustlink, Target, TargetRule},
+        state::AppState,
+        util::AliasNormalization,
+        RustlinkAlias,
+    };
 
     #[actix_web::test]
     async fn it_templates_no_items_with_no_format_string() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q=abcdefg".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -322,31 +853,20 @@ mod integration_tests {
 
     #[actix_web::test]
     async fn it_templates_no_items_with_format_string() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q={}".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -362,31 +882,20 @@ mod integration_tests {
 
     #[actix_web::test]
     async fn it_templates_no_items_with_no_format_string_but_has_params() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q=abcdefg".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -402,31 +911,20 @@ mod integration_tests {
 
     #[actix_web::test]
     async fn it_templates_items_with_format_string_and_params() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q={}".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -442,31 +940,20 @@ mod integration_tests {
 
     #[actix_web::test]
     async fn it_templates_items_with_format_string_and_params_with_spaces() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q={}".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -482,31 +969,20 @@ mod integration_tests {
 
     #[actix_web::test]
     async fn it_templates_multiple_input_parameters() {
-        let client = Client::connect(ClientConfig::new(vec![Endpoint::new(
-            "http://localhost:2379",
-        )]))
-        .await
-        .unwrap();
         let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
         rustlinks.insert(
             "test".to_string(),
             Rustlink {
                 url: "https://google.com/search?q={}&a={}".to_string(),
+                ..Default::default()
             },
         );
 
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(AppState {
-                    rustlinks: Arc::new(RwLock::new(rustlinks)),
-                    etcd_client: Arc::new(client),
-                    links_file: Arc::new(RwLock::new(None)),
-                    revision: Arc::new(RwLock::new(0)),
-                    read_only: true,
-                    js_source: Arc::new(RwLock::new("".to_string())),
-                    oauth_redirect_endpoint: "".to_string(),
-                    login_path: "".to_string(),
-                    oidc_providers: Arc::new(RwLock::new(vec![])),
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
                 }))
                 .service(redirect),
         )
@@ -520,5 +996,485 @@ mod integration_tests {
         );
     }
 
+    #[actix_web::test]
+    async fn it_appends_path_suffix_and_forwards_query_string() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "docs".to_string(),
+            Rustlink {
+                url: "https://docs.example.com/wiki".to_string(),
+                append_path: true,
+                forward_query: true,
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/docs/guides/setup?lang=en").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://docs.example.com/wiki/guides/setup?lang=en"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_falls_back_to_longest_prefix_with_remainder_as_params() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "payments".to_string(),
+            Rustlink {
+                url: "https://wiki.example.com/payments{/^}".to_string(),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "payments/runbook".to_string(),
+            Rustlink {
+                url: "https://runbooks.example.com/payments".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/payments/unknown").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://wiki.example.com/payments/unknown"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_resolves_canonicalized_aliases() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "oncall".to_string(),
+            Rustlink {
+                url: "https://wiki.example.com/oncall".to_string(),
+                display_alias: Some("OnCall".to_string()),
+                append_path: true,
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    alias_normalization: vec![
+                        AliasNormalization::Case,
+                        AliasNormalization::Separators,
+                    ],
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/On-Call/Payments").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://wiki.example.com/oncall/Payments"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_resolves_personal_paths_to_shared_aliases_without_a_user() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "standup".to_string(),
+            Rustlink {
+                url: "https://meet.example.com/standup".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+
+        for uri in ["/~/standup", "/me/standup"] {
+            let req = test::TestRequest::with_uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert!(resp.status().is_redirection());
+            assert_eq!(
+                resp.headers().get("location").unwrap().to_str().unwrap(),
+                "https://meet.example.com/standup"
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn it_chooses_targets_by_the_groups_of_signed_in_browsers() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "handbook".to_string(),
+            Rustlink {
+                url: "https://handbook.example.com".to_string(),
+                targets: vec![Target {
+                    url: "https://handbook.example.com/engineering".to_string(),
+                    rule: TargetRule::Group {
+                        group: "engineering".to_string(),
+                    },
+                }],
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    oidc_providers: Arc::new(RwLock::new(vec![OIDCProvider::for_tests()])),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let token = OIDCProvider::id_token_for_tests("ada@example.com", &["engineering"]);
+        let req = test::TestRequest::with_uri("/handbook")
+            .cookie(Cookie::new(ID_TOKEN_COOKIE, token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://handbook.example.com/engineering"
+        );
+
+        let req = test::TestRequest::with_uri("/handbook").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://handbook.example.com"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_resolves_personal_links_over_shared_ones_for_signed_in_browsers() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "standup".to_string(),
+            Rustlink {
+                url: "https://meet.example.com/standup".to_string(),
+                ..Default::default()
+            },
+        );
+        let mut personal: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        personal.insert(
+            "standup".to_string(),
+            Rustlink {
+                url: "https://meet.example.com/ada".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    personal_rustlinks: Arc::new(RwLock::new(HashMap::from([(
+                        "ada@example.com".to_string(),
+                        personal.into(),
+                    )]))),
+                    oidc_providers: Arc::new(RwLock::new(vec![OIDCProvider::for_tests()])),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let token = OIDCProvider::id_token_for_tests("ada@example.com", &[]);
+
+        for (uri, location) in [
+            ("/~/standup", "https://meet.example.com/ada"),
+            ("/me/standup", "https://meet.example.com/ada"),
+            ("/standup", "https://meet.example.com/standup"),
+        ] {
+            let req = test::TestRequest::with_uri(uri)
+                .cookie(Cookie::new(ID_TOKEN_COOKIE, token.clone()))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert!(resp.status().is_redirection());
+            assert_eq!(
+                resp.headers().get("location").unwrap().to_str().unwrap(),
+                location
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn it_refuses_links_depending_on_the_user_to_anonymous_requests() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "dotfiles".to_string(),
+            Rustlink {
+                url: "https://github.example.com/{$user.email}/dotfiles".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    oidc_providers: Arc::new(RwLock::new(vec![OIDCProvider::for_tests()])),
+                    login_path: "/login".to_string(),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/dotfiles").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().get("location").is_none());
+
+        let token = OIDCProvider::id_token_for_tests("ada@example.com", &[]);
+        let req = test::TestRequest::with_uri("/dotfiles")
+            .cookie(Cookie::new(ID_TOKEN_COOKIE, token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://github.example.com/ada%40example.com/dotfiles"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_lists_bundle_urls_as_json() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "incident".to_string(),
+            Rustlink {
+                bundle: vec![
+                    "https://status.example.com".to_string(),
+                    "https://chat.example.com/channels/incidents".to_string(),
+                    "https://{$host}/oncall".to_string(),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    public_url: "https://go.example.com".to_string(),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/incident?format=json")
+            .insert_header(("host", "evil.example.com/phish?"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp,
+            serde_json::json!({
+                "alias": "incident",
+                "urls": [
+                    "https://status.example.com",
+                    "https://chat.example.com/channels/incidents",
+                    "https://go.example.com/oncall",
+                ]
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_previews_links_with_a_suffix_unless_part_of_an_alias() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "payments".to_string(),
+            Rustlink {
+                url: "https://wiki.example.com/payments".to_string(),
+                description: Some("Payments team wiki".to_string()),
+                owners: vec!["payments@example.com".to_string()],
+                revision: Some(42),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "c++".to_string(),
+            Rustlink {
+                url: "https://en.cppreference.com".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    usage: Arc::new(HashMap::from([("payments".to_string(), 7)]).into()),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/payments+?format=json").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp,
+            serde_json::json!({
+                "alias": "payments",
+                "urls": ["https://wiki.example.com/payments"],
+                "description": "Payments team wiki",
+                "owners": ["payments@example.com"],
+                "revision": 42,
+                "usage": 7,
+                "redirect": "found",
+                "archived": false,
+                "deprecated": false,
+            })
+        );
+
+        let req = test::TestRequest::with_uri("/c++").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://en.cppreference.com"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_uses_fallback_for_unknown_aliases() {
+        let rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    fallbacks: vec![Fallback::Template(
+                        "https://intranet.example.com/search?q={^}".to_string(),
+                    )],
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/payments").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://intranet.example.com/search?q=payments"
+        );
+
+        // Fallbacks get the alias as it's looked up, not the path requested
+        let req = test::TestRequest::with_uri("/~/payments+").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://intranet.example.com/search?q=payments"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_treats_dangling_references_as_unknown_aliases() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "payments".to_string(),
+            Rustlink {
+                url: "alias:billing".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    fallbacks: vec![Fallback::Template(
+                        "https://intranet.example.com/search?q={^}".to_string(),
+                    )],
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/payments").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_redirection());
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            "https://intranet.example.com/search?q=payments"
+        );
+    }
+
+    #[actix_web::test]
+    async fn it_uses_the_configured_redirect_mode_without_caching() {
+        let mut rustlinks: HashMap<RustlinkAlias, Rustlink> = HashMap::new();
+        rustlinks.insert(
+            "standup".to_string(),
+            Rustlink {
+                url: "https://meet.example.com/standup".to_string(),
+                ..Default::default()
+            },
+        );
+        rustlinks.insert(
+            "handbook".to_string(),
+            Rustlink {
+                url: "https://handbook.example.com".to_string(),
+                redirect: RedirectMode::PermanentRedirect,
+                ..Default::default()
+            },
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState {
+                    rustlinks: Arc::new(RwLock::new(rustlinks.into())),
+                    ..AppState::for_tests()
+                }))
+                .service(redirect),
+        )
+        .await;
+        let req = test::TestRequest::with_uri("/standup").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers()
+                .get("cache-control")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "no-store"
+        );
+
+        let req = test::TestRequest::with_uri("/handbook").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+    }
+
     // TODO: additional URL encoding testss
 }
@@ -0,0 +1,173 @@
+use serde::Serialize;
+
+use crate::{
+    store::Store,
+    util::{self, AliasNormalization, NAMESPACE, PERSONAL_PREFIXES},
+};
+
+/// Where the API is mounted
+pub const API_PATH: &str = "/api/v1";
+
+/// Where the UI's static assets are mounted
+pub const UI_PATH: &str = "/_ui";
+
+/// An alias which can't be used, as requests for it are routed elsewhere
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Reserved {
+    /// The (canonicalized) first path segment which is reserved
+    pub segment: String,
+    /// What requests starting with the segment are routed to
+    pub reason: String,
+}
+
+/// Aliases which would be shadowed by the routes mounted ahead of
+/// [`crate::redirect::redirect`], or by personal link prefixes. An alias is
+/// reserved if its first segment is, since e.g. `api/docs` would be just as
+/// unreachable as `api`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReservedAliases {
+    reserved: Vec<Reserved>,
+    normalizations: Vec<AliasNormalization>,
+}
+
+impl ReservedAliases {
+    pub fn new(
+        login_path: &str,
+        oauth_callback_path: &str,
+        normalizations: &[AliasNormalization],
+    ) -> Self {
+        let mounted = [
+            (API_PATH, "the API"),
+            (UI_PATH, "the UI's assets"),
+            (login_path, "the login page"),
+            (oauth_callback_path, "the OAuth callback"),
+        ]
+        .map(|(path, reason)| (path.to_string(), reason.to_string()));
+        let personal =
+            PERSONAL_PREFIXES.map(|prefix| (prefix.to_string(), "personal links".to_string()));
+        let mut reserved: Vec<Reserved> = vec![];
+
+        for (path, reason) in mounted.into_iter().chain(personal) {
+            let Some(segment) = path.split('/').find(|segment| !segment.is_empty()) else {
+                continue;
+            };
+            let segment = util::canonicalize_alias(segment, normalizations);
+
+            if !reserved.iter().any(|existing| existing.segment == segment) {
+                reserved.push(Reserved { segment, reason });
+            }
+        }
+
+        ReservedAliases {
+            reserved,
+            normalizations: normalizations.to_vec(),
+        }
+    }
+
+    /// The reservation `alias` conflicts with, if any
+    pub fn check(&self, alias: &str) -> Option<&Reserved> {
+        let canonical = util::canonicalize_alias(alias, &self.normalizations);
+        let segment = canonical.split('/').next().unwrap_or_default();
+
+        self.reserved
+            .iter()
+            .find(|reserved| reserved.segment == segment)
+    }
+
+    /// Existing links which can't be reached because their alias is reserved,
+    /// e.g. by a change to the configured login path, or by shared links
+    /// predating personal link prefixes
+    pub async fn conflicts(
+        &self,
+        store: &dyn Store,
+    ) -> Result<Vec<(String, &Reserved)>, etcd_rs::Error> {
+        let (stored, _) = store.get_prefix(NAMESPACE).await?;
+
+        Ok(stored
+            .into_iter()
+            .map(|kv| util::key_to_alias(&kv.key))
+            .filter_map(|alias| self.check(&alias).map(|reserved| (alias, reserved)))
+            .collect())
+    }
+
+    /// Warn about existing links which can't be reached, so that they can be
+    /// renamed
+    pub async fn report_conflicts(&self, store: &dyn Store) {
+        match self.conflicts(store).await {
+            Ok(conflicts) => {
+                for (alias, reserved) in conflicts {
+                    eprintln!(
+                        "Link `{}` is unreachable, as `{}` is reserved for {}, rename it to keep using it",
+                        alias, reserved.segment, reserved.reason
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to check links for reserved aliases: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::store::memory::InMemoryStore;
+
+    #[test]
+    fn it_reserves_the_first_segment_of_mounted_paths() {
+        let reserved = ReservedAliases::new(
+            "/login",
+            "/api/v1/oauth/callback",
+            &[AliasNormalization::Case],
+        );
+
+        assert_eq!(reserved.check("api").unwrap().reason, "the API");
+        assert_eq!(reserved.check("API/docs").unwrap().reason, "the API");
+        assert_eq!(reserved.check("_ui").unwrap().reason, "the UI's assets");
+        assert_eq!(reserved.check("Login").unwrap().reason, "the login page");
+        assert_eq!(reserved.check("~").unwrap().reason, "personal links");
+        assert_eq!(
+            reserved.check("me/standup").unwrap().reason,
+            "personal links"
+        );
+        assert_eq!(reserved.check("apis"), None);
+        assert_eq!(reserved.check("payments/login"), None);
+    }
+
+    #[actix_web::test]
+    async fn it_finds_existing_links_shadowed_by_reservations() {
+        let store = InMemoryStore::default();
+
+        for alias in ["me/standup", "~/retro", "api", "payments/me", "meetings"] {
+            store
+                .put(format!("{}{}", NAMESPACE, alias), b"{}".to_vec())
+                .await
+                .unwrap();
+        }
+        let reserved = ReservedAliases::new("/login", "/oauth/callback", &[]);
+        let conflicts = reserved.conflicts(&store).await.unwrap();
+
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(|(alias, reserved)| (alias.as_str(), reserved.reason.as_str()))
+                .collect::<Vec<(&str, &str)>>(),
+            vec![
+                ("api", "the API"),
+                ("me/standup", "personal links"),
+                ("~/retro", "personal links")
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reserves_configured_callback_paths() {
+        let reserved = ReservedAliases::new("/login", "/oauth2/callback", &[]);
+
+        assert_eq!(
+            reserved.check("oauth2").unwrap().reason,
+            "the OAuth callback"
+        );
+    }
+}
@@ -1,4 +1,449 @@
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+use chrono::{DateTime, FixedOffset, Utc};
+
+/// Prefix of URLs which refer to another alias rather than a destination.
+/// References are followed as the link they end at is configured (e.g. its
+/// `append_path`), so links referring to others can't configure that.
+pub const REFERENCE_PREFIX: &str = "alias:";
+
+/// The form tags are stored (and looked up) in, so that e.g. `Meetings` and
+/// `meetings ` are the same tag
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Rustlink {
+    /// Optional for bundle links, which have `bundle` instead
+    #[serde(default)]
+    pub url: String,
+    /// What the link is for, shown when previewing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Who to ask about the link
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// Labels for finding and grouping links, normalized to lowercase
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// When, and by whom, the link was created and last updated. Set from the
+    /// requesting identity when the link is stored, rather than taken from
+    /// the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+    /// Kept around for reference, but no longer maintained
+    #[serde(default)]
+    pub archived: bool,
+    /// Left behind at an alias the link was renamed from, referring to its new
+    /// alias so that the old one keeps working
+    #[serde(default)]
+    pub deprecated: bool,
+    /// The etcd revision the link was last modified at, set when it's synced
+    /// rather than stored with the link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+    /// The alias as originally spelled when the link was created, as the key
+    /// it's stored under may have been canonicalized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_alias: Option<String>,
+    /// Append any path following the alias (e.g. `docs/guides/setup` for the
+    /// alias `docs`) to the destination URL
+    #[serde(default)]
+    pub append_path: bool,
+    /// Merge the query string of the incoming request into the destination URL
+    #[serde(default)]
+    pub forward_query: bool,
+    /// How clients are sent to the destination
+    #[serde(default)]
+    pub redirect: RedirectMode,
+    /// Targets which replace `url` while they're active, the first active
+    /// target in the list wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduledTarget>,
+    /// Alternative destinations chosen per request, by rule or by weight
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Target>,
+    /// URLs (templates) opened together, making this a bundle link which
+    /// shows a page listing them rather than redirecting
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bundle: Vec<String>,
+}
+
+/// A destination which is only active within a window of time, with either end
+/// of the window optional
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScheduledTarget {
+    pub url: String,
+    /// RFC 3339 timestamp (with timezone offset) the target is active from
+    #[serde(default)]
+    pub valid_from: Option<DateTime<FixedOffset>>,
+    /// RFC 3339 timestamp (with timezone offset) the target is active until
+    #[serde(default)]
+    pub valid_until: Option<DateTime<FixedOffset>>,
+}
+
+impl ScheduledTarget {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.valid_from.map_or(true, |from| from <= now)
+            && self.valid_until.map_or(true, |until| now < until)
+    }
+}
+
+/// A destination chosen per request when its rule applies
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Target {
     pub url: String,
+    pub rule: TargetRule,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TargetRule {
+    /// Requests with header `name` containing `value` (case-insensitively),
+    /// e.g. a `User-Agent` containing `Android`
+    Header { name: String, value: String },
+    /// Requests made by a member of `group`, as claimed by the ID token they
+    /// send (as a bearer token, or the cookie signing in sets)
+    Group { group: String },
+    /// Requests not matched by any other rule are split between weighted
+    /// targets in proportion to their weights
+    Weighted { weight: u32 },
+}
+
+/// Which of a link's destinations was chosen for a request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    Default,
+    Scheduled(usize),
+    Target(usize),
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selection::Default => write!(f, "default"),
+            Selection::Scheduled(index) => write!(f, "schedule.{index}"),
+            Selection::Target(index) => write!(f, "targets.{index}"),
+        }
+    }
+}
+
+impl Rustlink {
+    /// The index of the scheduled target active at `now`, if any
+    pub fn active_target(&self, now: DateTime<Utc>) -> Option<usize> {
+        self.schedule
+            .iter()
+            .position(|target| target.is_active(now))
+    }
+
+    /// The URL in effect at `now`, either that of the active scheduled target,
+    /// or the default
+    pub fn active_url(&self, now: DateTime<Utc>) -> &str {
+        match self.active_target(now) {
+            Some(index) => &self.schedule[index].url,
+            None => &self.url,
+        }
+    }
+
+    /// Every URL (template) the link can send clients to
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str())
+            .chain(self.schedule.iter().map(|target| target.url.as_str()))
+            .chain(self.targets.iter().map(|target| target.url.as_str()))
+            .chain(self.bundle.iter().map(String::as_str))
+    }
+
+    /// Whether choosing a target requires knowing the groups of the user
+    pub fn has_group_rules(&self) -> bool {
+        self.targets
+            .iter()
+            .any(|target| matches!(target.rule, TargetRule::Group { .. }))
+    }
+
+    /// Choose the destination for a request. The first header or group rule
+    /// which matches wins, then an active scheduled target, then a weighted
+    /// target, and finally `url`.
+    ///
+    /// `sticky` is a weighted target previously chosen for the same client,
+    /// which is kept if it's still valid, otherwise `roll` is given the sum of
+    /// weights and returns a number below it.
+    pub fn select<'a>(
+        &self,
+        now: DateTime<Utc>,
+        header: impl Fn(&str) -> Option<&'a str>,
+        groups: &[String],
+        sticky: Option<usize>,
+        roll: impl FnOnce(u32) -> u32,
+    ) -> Selection {
+        let matched = self.targets.iter().position(|target| match &target.rule {
+            TargetRule::Header { name, value } => header(name).map_or(false, |header| {
+                header.to_lowercase().contains(&value.to_lowercase())
+            }),
+            TargetRule::Group { group } => groups.contains(group),
+            TargetRule::Weighted { .. } => false,
+        });
+
+        if let Some(index) = matched {
+            return Selection::Target(index);
+        }
+        if let Some(index) = self.active_target(now) {
+            return Selection::Scheduled(index);
+        }
+        if let Some(index) = sticky
+            && self.weight(index) > 0
+        {
+            return Selection::Target(index);
+        }
+        let total: u32 = (0..self.targets.len())
+            .map(|index| self.weight(index))
+            .sum();
+
+        if total == 0 {
+            return Selection::Default;
+        }
+        let mut rolled = roll(total);
+
+        for index in 0..self.targets.len() {
+            let weight = self.weight(index);
+
+            if rolled < weight {
+                return Selection::Target(index);
+            }
+            rolled -= weight;
+        }
+        Selection::Default
+    }
+
+    /// The URL of the destination chosen by [`Rustlink::select`]
+    pub fn selected_url(&self, selection: Selection) -> &str {
+        match selection {
+            Selection::Default => &self.url,
+            Selection::Scheduled(index) => &self.schedule[index].url,
+            Selection::Target(index) => &self.targets[index].url,
+        }
+    }
+
+    /// The weight of the target at `index`, zero if it isn't a weighted target
+    pub fn weight(&self, index: usize) -> u32 {
+        match self.targets.get(index).map(|target| &target.rule) {
+            Some(TargetRule::Weighted { weight }) => *weight,
+            _ => 0,
+        }
+    }
+
+    /// The alias this link currently refers to, if its URL is of the form
+    /// `alias:<other>`
+    pub fn reference(&self) -> Option<&str> {
+        self.active_url(Utc::now()).strip_prefix(REFERENCE_PREFIX)
+    }
+
+    /// Every alias this link refers to at some point, e.g. through scheduled
+    /// URLs which aren't active yet
+    pub fn references(&self) -> impl Iterator<Item = &str> {
+        self.urls()
+            .filter_map(|url| url.strip_prefix(REFERENCE_PREFIX))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectMode {
+    /// 302, the default, since browsers cache permanent redirects aggressively
+    #[default]
+    #[serde(alias = "302")]
+    Found,
+    /// 301
+    #[serde(alias = "301")]
+    MovedPermanently,
+    /// 307
+    #[serde(alias = "307")]
+    TemporaryRedirect,
+    /// 308
+    #[serde(alias = "308")]
+    PermanentRedirect,
+    /// An HTML page showing the destination before a meta refresh redirects to
+    /// it
+    Interstitial,
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn it_deserializes_links_stored_before_metadata() {
+        let rustlink: Rustlink =
+            serde_json::from_str(r#"{"url": "https://docs.example.com"}"#).unwrap();
+        assert_eq!(
+            rustlink,
+            Rustlink {
+                url: "https://docs.example.com".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn it_round_trips_metadata() {
+        let rustlink = Rustlink {
+            url: "https://docs.example.com".to_string(),
+            owners: vec!["ada@example.com".to_string()],
+            description: Some("Engineering docs".to_string()),
+            tags: vec!["docs".to_string(), "eng".to_string()],
+            created_at: Some(at("2024-03-01T09:00:00Z")),
+            created_by: Some("ada@example.com".to_string()),
+            updated_at: Some(at("2024-03-02T09:00:00Z")),
+            updated_by: Some("grace@example.com".to_string()),
+            archived: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&rustlink).unwrap();
+        assert_eq!(serde_json::from_str::<Rustlink>(&json).unwrap(), rustlink);
+    }
+
+    #[test]
+    fn it_uses_the_first_active_scheduled_target() {
+        let rustlink = Rustlink {
+            url: "https://docs.example.com/planning".to_string(),
+            schedule: vec![
+                ScheduledTarget {
+                    url: "https://docs.example.com/planning-q4".to_string(),
+                    valid_from: None,
+                    valid_until: Some(
+                        DateTime::parse_from_rfc3339("2027-01-01T00:00:00-08:00").unwrap(),
+                    ),
+                },
+                ScheduledTarget {
+                    url: "https://docs.example.com/planning-q1".to_string(),
+                    valid_from: Some(
+                        DateTime::parse_from_rfc3339("2027-01-01T00:00:00-08:00").unwrap(),
+                    ),
+                    valid_until: Some(
+                        DateTime::parse_from_rfc3339("2027-04-01T00:00:00-07:00").unwrap(),
+                    ),
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            rustlink.active_url(at("2026-12-31T23:59:59-08:00")),
+            "https://docs.example.com/planning-q4"
+        );
+        // Still 2026 in UTC-8, but already 2027 in UTC
+        assert_eq!(
+            rustlink.active_url(at("2027-01-01T07:00:00Z")),
+            "https://docs.example.com/planning-q4"
+        );
+        assert_eq!(
+            rustlink.active_url(at("2027-01-01T08:00:00Z")),
+            "https://docs.example.com/planning-q1"
+        );
+        assert_eq!(
+            rustlink.active_url(at("2027-04-01T07:00:00Z")),
+            "https://docs.example.com/planning"
+        );
+    }
+
+    fn split_link() -> Rustlink {
+        Rustlink {
+            url: "https://example.com/default".to_string(),
+            targets: vec![
+                Target {
+                    url: "https://play.google.com/store/apps/details?id=com.example".to_string(),
+                    rule: TargetRule::Header {
+                        name: "user-agent".to_string(),
+                        value: "android".to_string(),
+                    },
+                },
+                Target {
+                    url: "https://example.com/internal".to_string(),
+                    rule: TargetRule::Group {
+                        group: "staff".to_string(),
+                    },
+                },
+                Target {
+                    url: "https://example.com/a".to_string(),
+                    rule: TargetRule::Weighted { weight: 90 },
+                },
+                Target {
+                    url: "https://example.com/b".to_string(),
+                    rule: TargetRule::Weighted { weight: 10 },
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_selects_targets_by_rule_before_weight() {
+        let rustlink = split_link();
+        let android = |name: &str| match name {
+            "user-agent" => Some("Mozilla/5.0 (Linux; Android 14)"),
+            _ => None,
+        };
+        let staff = vec!["staff".to_string()];
+
+        assert_eq!(
+            rustlink.select(Utc::now(), android, &staff, None, |_| 95),
+            Selection::Target(0)
+        );
+        assert_eq!(
+            rustlink.select(Utc::now(), |_| None, &staff, None, |_| 95),
+            Selection::Target(1)
+        );
+    }
+
+    #[test]
+    fn it_selects_weighted_targets_by_roll_or_sticky_choice() {
+        let rustlink = split_link();
+
+        assert_eq!(
+            rustlink.select(
+                Utc::now(),
+                |_| None,
+                &[],
+                None,
+                |total| {
+                    assert_eq!(total, 100);
+                    89
+                }
+            ),
+            Selection::Target(2)
+        );
+        assert_eq!(
+            rustlink.select(Utc::now(), |_| None, &[], None, |_| 90),
+            Selection::Target(3)
+        );
+        assert_eq!(
+            rustlink.select(Utc::now(), |_| None, &[], Some(3), |_| 0),
+            Selection::Target(3)
+        );
+        // Sticky choices which are no longer weighted targets are re-rolled
+        assert_eq!(
+            rustlink.select(Utc::now(), |_| None, &[], Some(1), |_| 0),
+            Selection::Target(2)
+        );
+        assert_eq!(
+            Rustlink::default().select(Utc::now(), |_| None, &[], None, |_| 0),
+            Selection::Default
+        );
+    }
+
+    #[test]
+    fn it_deserializes_links_without_a_schedule() {
+        let rustlink: Rustlink = serde_json::from_str(r#"{"url": "https://example.com"}"#).unwrap();
+        assert!(rustlink.schedule.is_empty());
+        assert_eq!(rustlink.active_target(Utc::now()), None);
+    }
 }
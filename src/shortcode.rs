@@ -0,0 +1,101 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{self, AliasNormalization};
+
+/// Digits and lowercase letters, without those easily mistaken for each other
+/// (`0`/`o`, `1`/`l`)
+pub const DEFAULT_ALPHABET: &str = "23456789abcdefghijkmnpqrstuvwxyz";
+
+pub const DEFAULT_MIN_LENGTH: usize = 6;
+
+/// Codes tried before giving up on finding an unused one
+pub const MAX_ATTEMPTS: usize = 12;
+
+/// Collisions tolerated at a length before codes get longer
+const ATTEMPTS_PER_LENGTH: usize = 3;
+
+/// How aliases are minted for links created without one
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ShortCodes {
+    pub min_length: usize,
+    pub alphabet: Vec<char>,
+}
+
+impl Default for ShortCodes {
+    fn default() -> Self {
+        ShortCodes {
+            min_length: DEFAULT_MIN_LENGTH,
+            alphabet: DEFAULT_ALPHABET.chars().collect(),
+        }
+    }
+}
+
+impl ShortCodes {
+    /// Check that every code this can generate is its own canonical form, so
+    /// that distinct codes can't collide after normalization
+    pub fn validate(&self, normalizations: &[AliasNormalization]) -> Result<(), String> {
+        if self.min_length == 0 {
+            return Err("Short codes must be at least one character long".to_string());
+        }
+        if self.alphabet.len() < 2 {
+            return Err("Short code alphabets need at least two characters".to_string());
+        }
+        for c in self.alphabet.iter() {
+            let c = c.to_string();
+
+            if c == "/" || util::canonicalize_alias(&c, normalizations) != c {
+                return Err(format!(
+                    "Short code alphabet contains `{}`, which changes when aliases are normalized",
+                    c
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate a random code for the given attempt at finding an unused one,
+    /// getting longer as attempts collide with existing aliases
+    pub fn generate(&self, attempt: usize) -> String {
+        let length = self.min_length + attempt / ATTEMPTS_PER_LENGTH;
+        let mut rng = rand::thread_rng();
+
+        (0..length)
+            .filter_map(|_| self.alphabet.choose(&mut rng))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_generates_codes_from_the_alphabet_getting_longer_with_attempts() {
+        let short_codes = ShortCodes::default();
+
+        for attempt in 0..MAX_ATTEMPTS {
+            let code = short_codes.generate(attempt);
+            assert_eq!(
+                code.len(),
+                DEFAULT_MIN_LENGTH + attempt / ATTEMPTS_PER_LENGTH
+            );
+            assert!(code.chars().all(|c| DEFAULT_ALPHABET.contains(c)));
+        }
+        assert!(!DEFAULT_ALPHABET.contains(['0', 'O', 'o', '1', 'l', 'I']));
+    }
+
+    #[test]
+    fn it_rejects_alphabets_changed_by_normalization() {
+        let short_codes = ShortCodes {
+            min_length: 4,
+            alphabet: "abcXYZ".chars().collect(),
+        };
+
+        assert!(short_codes.validate(&[]).is_ok());
+        assert!(short_codes.validate(&[AliasNormalization::Case]).is_err());
+        assert!(ShortCodes::default()
+            .validate(&[AliasNormalization::Case, AliasNormalization::Separators])
+            .is_ok());
+    }
+}
@@ -2,43 +2,147 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::sync::Arc;
 
-use etcd_rs::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
 use super::RustlinkAlias;
-use crate::{oidc, rustlink};
+use crate::{
+    collection::Collections,
+    fallback::Fallback,
+    index::RustlinkIndex,
+    oidc,
+    reserved::ReservedAliases,
+    rustlink,
+    shortcode::ShortCodes,
+    store::Store,
+    template::TemplateVariable,
+    trash::Trash,
+    usage::Usage,
+    util::{self, AliasNormalization},
+};
 
 pub struct AppState {
-    pub(crate) rustlinks: Arc<RwLock<HashMap<RustlinkAlias, rustlink::Rustlink>>>,
+    pub(crate) rustlinks: Arc<RwLock<RustlinkIndex>>,
     pub(crate) revision: Arc<RwLock<i64>>,
-    pub(crate) etcd_client: Arc<Client>,
+    /// Where links are read from and written to by the API
+    pub(crate) store: Arc<dyn Store>,
     pub(crate) links_file: Arc<RwLock<Option<File>>>,
     pub(crate) read_only: bool,
     pub(crate) oauth_redirect_endpoint: String,
     pub(crate) js_source: Arc<RwLock<String>>,
     pub(crate) oidc_providers: Arc<RwLock<Vec<oidc::provider::OIDCProvider>>>,
     pub(crate) login_path: String,
+    pub(crate) alias_normalization: Vec<AliasNormalization>,
+    pub(crate) fallbacks: Vec<Fallback>,
+    /// Shared with upstream servers, to ask each other not to use fallbacks
+    pub(crate) fallback_secret: Option<String>,
+    /// Users whose personal links are synced to this node
+    pub(crate) personal_owners: Vec<String>,
+    pub(crate) personal_rustlinks: Arc<RwLock<HashMap<String, RustlinkIndex>>>,
+    pub(crate) collections: Arc<RwLock<Collections>>,
+    /// Values set by operators for use in templates
+    pub(crate) template_variables: Vec<TemplateVariable>,
+    /// Redirects served by this node per alias
+    pub(crate) usage: Arc<Usage>,
+    pub(crate) short_codes: ShortCodes,
+    pub(crate) reserved: ReservedAliases,
+    /// The URL users reach this node at, as configured, without a trailing
+    /// slash
+    pub(crate) public_url: String,
+    /// How long deleted links are kept in the trash before they're purged
+    pub(crate) trash_retention: chrono::Duration,
+    pub(crate) trash: Arc<RwLock<Trash>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SerdeAppState {
     pub(crate) rustlinks: HashMap<RustlinkAlias, rustlink::Rustlink>,
     pub(crate) revision: i64,
+    #[serde(default)]
+    pub(crate) personal_rustlinks: HashMap<String, HashMap<RustlinkAlias, rustlink::Rustlink>>,
+    #[serde(default)]
+    pub(crate) usage: HashMap<RustlinkAlias, u64>,
+    #[serde(default)]
+    pub(crate) collections: Collections,
 }
 
 impl AppState {
     pub async fn from(&self) -> SerdeAppState {
-        let mut rustlinks: HashMap<RustlinkAlias, rustlink::Rustlink> = HashMap::new();
-
-        let links = self.rustlinks.read().await;
-        rustlinks.extend(links.clone());
+        let rustlinks: HashMap<RustlinkAlias, rustlink::Rustlink> =
+            (**self.rustlinks.read().await).clone();
 
         let revision = *self.revision.read().await;
+        let personal_rustlinks = self
+            .personal_rustlinks
+            .read()
+            .await
+            .iter()
+            .map(|(owner, rustlinks)| (owner.clone(), (**rustlinks).clone()))
+            .collect();
+        let usage = self.usage.snapshot();
+        let collections = self.collections.read().await.clone();
 
         SerdeAppState {
             rustlinks,
             revision,
+            personal_rustlinks,
+            usage,
+            collections,
+        }
+    }
+
+    /// The personal links of `owner`, from memory if they're synced to this
+    /// node, otherwise fetched from the store
+    pub async fn personal_rustlinks(&self, owner: &str) -> Result<RustlinkIndex, etcd_rs::Error> {
+        if let Some(rustlinks) = self.personal_rustlinks.read().await.get(owner) {
+            return Ok(rustlinks.clone());
+        }
+        let prefix = util::personal_prefix(owner);
+        let (values, _) = self.store.get_prefix(&prefix).await?;
+        let mut rustlinks = RustlinkIndex::default();
+
+        for value in values {
+            if let Some(alias) = value.key.strip_prefix(&prefix) {
+                let _ = rustlinks.apply_put(
+                    alias,
+                    &value.value,
+                    value.mod_revision,
+                    &self.alias_normalization,
+                );
+            }
+        }
+        Ok(rustlinks)
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State with nothing configured and an empty in-memory store, for tests
+    /// to override what they need with struct update syntax
+    pub fn for_tests() -> Self {
+        AppState {
+            rustlinks: Arc::new(RwLock::new(Default::default())),
+            revision: Arc::new(RwLock::new(0)),
+            store: Arc::new(crate::store::memory::InMemoryStore::default()),
+            links_file: Arc::new(RwLock::new(None)),
+            read_only: false,
+            oauth_redirect_endpoint: "".to_string(),
+            js_source: Arc::new(RwLock::new("".to_string())),
+            oidc_providers: Arc::new(RwLock::new(vec![])),
+            login_path: "".to_string(),
+            alias_normalization: vec![],
+            fallbacks: vec![],
+            fallback_secret: None,
+            personal_owners: vec![],
+            personal_rustlinks: Default::default(),
+            collections: Default::default(),
+            template_variables: vec![],
+            usage: Default::default(),
+            short_codes: Default::default(),
+            reserved: Default::default(),
+            public_url: "http://go".to_string(),
+            trash_retention: chrono::Duration::days(crate::trash::DEFAULT_RETENTION_DAYS.into()),
+            trash: Default::default(),
         }
     }
 }
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use etcd_rs::{
+    Client, DeleteRequest, KeyRange, KeyValueOp, PutRequest, RangeRequest, TxnCmp, TxnRequest,
+};
+
+use super::{Condition, Operation, Store, StoredValue};
+
+#[async_trait]
+impl Store for Client {
+    async fn get(&self, key: &str) -> Result<Option<StoredValue>, etcd_rs::Error> {
+        let response = KeyValueOp::get(self, RangeRequest::new(KeyRange::key(key))).await?;
+
+        Ok(response.kvs.into_iter().next().map(|kv| StoredValue {
+            key: kv.key_str().to_string(),
+            mod_revision: kv.mod_revision,
+            value: kv.value,
+        }))
+    }
+
+    async fn get_prefix(&self, prefix: &str) -> Result<(Vec<StoredValue>, i64), etcd_rs::Error> {
+        let response = self.get_by_prefix(prefix).await?;
+        let revision = response.header.revision();
+        let values = response
+            .kvs
+            .into_iter()
+            .map(|kv| StoredValue {
+                key: kv.key_str().to_string(),
+                mod_revision: kv.mod_revision,
+                value: kv.value,
+            })
+            .collect();
+
+        Ok((values, revision))
+    }
+
+    async fn txn(
+        &self,
+        conditions: Vec<Condition>,
+        operations: Vec<Operation>,
+    ) -> Result<Option<i64>, etcd_rs::Error> {
+        let mut txn = TxnRequest::new();
+
+        for condition in conditions {
+            txn = match condition {
+                Condition::Absent(key) => txn.when_version(KeyRange::key(key), TxnCmp::Equal, 0),
+                Condition::Present(key) => txn.when_version(KeyRange::key(key), TxnCmp::Greater, 0),
+                Condition::ModRevision(key, revision) => {
+                    txn.when_mod_revision(KeyRange::key(key), TxnCmp::Equal, revision as usize)
+                }
+            };
+        }
+        for operation in operations {
+            txn = match operation {
+                Operation::Put(key, value) => txn.and_then(PutRequest::new(key, value)),
+                Operation::Delete(key) => txn.and_then(DeleteRequest::new(KeyRange::key(key))),
+            };
+        }
+        let response = KeyValueOp::txn(self, txn).await?;
+
+        Ok(response.succeeded.then(|| response.header.revision()))
+    }
+}
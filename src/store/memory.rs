@@ -0,0 +1,106 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::sync::{Mutex, RwLock};
+
+use super::{Condition, Operation, Store, StoredValue};
+use crate::{
+    index::RustlinkIndex,
+    util::{AliasNormalization, NAMESPACE},
+};
+
+#[derive(Default)]
+struct Inner {
+    values: BTreeMap<String, StoredValue>,
+    revision: i64,
+}
+
+/// A store kept in memory, which applies writes to the shared links straight
+/// to an index, standing in for etcd and the worker's watch of it
+#[derive(Default)]
+pub struct InMemoryStore {
+    inner: Mutex<Inner>,
+    index: Option<(Arc<RwLock<RustlinkIndex>>, Vec<AliasNormalization>)>,
+}
+
+impl InMemoryStore {
+    pub fn new(index: Arc<RwLock<RustlinkIndex>>, normalizations: Vec<AliasNormalization>) -> Self {
+        InMemoryStore {
+            inner: Default::default(),
+            index: Some((index, normalizations)),
+        }
+    }
+}
+
+#[async_trait]
+impl Store for InMemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<StoredValue>, etcd_rs::Error> {
+        Ok(self.inner.lock().await.values.get(key).cloned())
+    }
+
+    async fn get_prefix(&self, prefix: &str) -> Result<(Vec<StoredValue>, i64), etcd_rs::Error> {
+        let inner = self.inner.lock().await;
+        let values = inner
+            .values
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(_, value)| value.clone())
+            .collect();
+
+        Ok((values, inner.revision))
+    }
+
+    async fn txn(
+        &self,
+        conditions: Vec<Condition>,
+        operations: Vec<Operation>,
+    ) -> Result<Option<i64>, etcd_rs::Error> {
+        let mut inner = self.inner.lock().await;
+        let holds = conditions.iter().all(|condition| match condition {
+            Condition::Absent(key) => !inner.values.contains_key(key),
+            Condition::Present(key) => inner.values.contains_key(key),
+            Condition::ModRevision(key, revision) => {
+                inner.values.get(key).map_or(0, |value| value.mod_revision) == *revision
+            }
+        });
+
+        if !holds {
+            return Ok(None);
+        }
+        inner.revision += 1;
+        let revision = inner.revision;
+
+        for operation in operations {
+            match operation {
+                Operation::Put(key, value) => {
+                    if let Some((index, normalizations)) = &self.index
+                        && let Some(alias) = key.strip_prefix(NAMESPACE)
+                    {
+                        let _ =
+                            index
+                                .write()
+                                .await
+                                .apply_put(alias, &value, revision, normalizations);
+                    }
+                    inner.values.insert(
+                        key.clone(),
+                        StoredValue {
+                            key,
+                            value,
+                            mod_revision: revision,
+                        },
+                    );
+                }
+                Operation::Delete(key) => {
+                    if let Some((index, normalizations)) = &self.index
+                        && let Some(alias) = key.strip_prefix(NAMESPACE)
+                    {
+                        index.write().await.apply_delete(alias, normalizations);
+                    }
+                    inner.values.remove(&key);
+                }
+            }
+        }
+        Ok(Some(revision))
+    }
+}
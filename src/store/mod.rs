@@ -0,0 +1,57 @@
+pub mod etcd;
+#[cfg(test)]
+pub mod memory;
+
+use async_trait::async_trait;
+
+/// A key and its value as stored, along with the revision it was last
+/// modified at
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredValue {
+    pub key: String,
+    pub value: Vec<u8>,
+    pub mod_revision: i64,
+}
+
+/// What must hold for a transaction's operations to be applied
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// The key doesn't exist
+    Absent(String),
+    /// The key exists
+    Present(String),
+    /// The key was last modified at the given revision
+    ModRevision(String, i64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    Put(String, Vec<u8>),
+    Delete(String),
+}
+
+/// The key-value operations the API needs, so that they can be served by
+/// something other than etcd (e.g. in tests). Watching for changes is still
+/// done against etcd directly, by the [`crate::worker::Worker`].
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<StoredValue>, etcd_rs::Error>;
+
+    /// Every key under `prefix`, along with the revision of the store they
+    /// were read at
+    async fn get_prefix(&self, prefix: &str) -> Result<(Vec<StoredValue>, i64), etcd_rs::Error>;
+
+    /// Apply `operations` atomically if every condition holds, returning the
+    /// resulting revision, or `None` if a condition didn't hold
+    async fn txn(
+        &self,
+        conditions: Vec<Condition>,
+        operations: Vec<Operation>,
+    ) -> Result<Option<i64>, etcd_rs::Error>;
+
+    async fn put(&self, key: String, value: Vec<u8>) -> Result<i64, etcd_rs::Error> {
+        self.txn(vec![], vec![Operation::Put(key, value)])
+            .await
+            .map(Option::unwrap_or_default)
+    }
+}
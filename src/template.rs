@@ -0,0 +1,346 @@
+use std::{fmt::Write, str::FromStr};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Utc,
+};
+use serde::{Deserialize, Serialize};
+use urlencoding::encode;
+
+use crate::{errors::TemplateError, oidc::identity::Identity};
+
+/// Opens a server-side variable in a URL template, e.g. `{$user.login}` or
+/// `{$date:%Y-%m-%d}`
+const VARIABLE_START: &str = "{$";
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+const USER_VARIABLES: [&str; 4] = ["user.login", "user.email", "user.subject", "user.issuer"];
+
+/// What destinations must start with, so that links can't e.g. run
+/// `javascript:` on our origin
+const ALLOWED_SCHEMES: [&str; 2] = ["http://", "https://"];
+
+/// A value set by operators, available to templates as `{$config.<name>}`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TemplateVariable {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for TemplateVariable {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(TemplateVariable {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err("Template variables must be of the form name=value"),
+        }
+    }
+}
+
+/// Everything server-side variables are resolved from, for a single request
+pub struct Variables<'a> {
+    pub user: Option<&'a Identity>,
+    pub now: DateTime<Utc>,
+    /// The host (and port) of the public URL this server is configured with
+    pub host: &'a str,
+    pub config: &'a [TemplateVariable],
+}
+
+/// A variable in a template, spanning `start..end`
+struct Placeholder<'a> {
+    start: usize,
+    end: usize,
+    name: &'a str,
+    format: Option<&'a str>,
+}
+
+fn placeholders(template: &str) -> Result<Vec<Placeholder<'_>>, TemplateError> {
+    let mut placeholders = vec![];
+    let mut offset = 0;
+
+    while let Some(found) = template[offset..].find(VARIABLE_START) {
+        let start = offset + found;
+        let inner_start = start + VARIABLE_START.len();
+        let inner_end = template[inner_start..]
+            .find('}')
+            .map(|end| inner_start + end)
+            .ok_or(TemplateError::Unclosed(start))?;
+        let (name, format) = match template[inner_start..inner_end].split_once(':') {
+            Some((name, format)) => (name, Some(format)),
+            None => (&template[inner_start..inner_end], None),
+        };
+
+        placeholders.push(Placeholder {
+            start,
+            end: inner_end + 1,
+            name,
+            format,
+        });
+        offset = inner_end + 1;
+    }
+    Ok(placeholders)
+}
+
+/// Check that every variable in `template` exists, and any formats given are
+/// valid, so that links are rejected when they're written rather than failing
+/// when they're used
+pub fn validate(template: &str, config: &[TemplateVariable]) -> Result<(), TemplateError> {
+    for placeholder in placeholders(template)? {
+        let name = placeholder.name;
+
+        match name {
+            "date" | "time" => {
+                if let Some(format) = placeholder.format
+                    && StrftimeItems::new(format).any(|item| item == Item::Error)
+                {
+                    return Err(TemplateError::InvalidFormat(
+                        name.to_string(),
+                        format.to_string(),
+                    ));
+                }
+            }
+            _ => {
+                let known = USER_VARIABLES.contains(&name)
+                    || name == "timestamp"
+                    || name == "host"
+                    || name
+                        .strip_prefix("config.")
+                        .map_or(false, |key| config.iter().any(|v| v.name == key));
+
+                if !known {
+                    return Err(TemplateError::UnknownVariable(name.to_string()));
+                }
+                if placeholder.format.is_some() {
+                    return Err(TemplateError::UnexpectedFormat(name.to_string()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that `url` is a web page, by its scheme
+pub fn check_scheme(url: &str) -> Result<(), TemplateError> {
+    let allowed = ALLOWED_SCHEMES.iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    });
+
+    match allowed {
+        true => Ok(()),
+        false => Err(TemplateError::UnsupportedScheme(url.to_string())),
+    }
+}
+
+/// Check that `template` is a web page whatever its variables are. Only
+/// configured variables are known before a request, so templates have to
+/// start with their scheme, or a configured variable which does.
+pub fn validate_scheme(template: &str, config: &[TemplateVariable]) -> Result<(), TemplateError> {
+    let mut known = String::with_capacity(template.len());
+    let mut offset = 0;
+
+    for placeholder in placeholders(template)? {
+        known.push_str(&template[offset..placeholder.start]);
+        offset = placeholder.end;
+
+        let configured = placeholder
+            .name
+            .strip_prefix("config.")
+            .and_then(|name| config.iter().find(|variable| variable.name == name));
+
+        match configured {
+            Some(variable) => known.push_str(&variable.value),
+            // Never part of a scheme
+            None => known.push_str(VARIABLE_START),
+        }
+    }
+    known.push_str(&template[offset..]);
+
+    check_scheme(&known).map_err(|_| TemplateError::UnsupportedScheme(template.to_string()))
+}
+
+/// Whether `template` refers to the requesting user, who then needs to be
+/// identified to render it
+pub fn uses_user(template: &str) -> bool {
+    placeholders(template).map_or(false, |placeholders| {
+        placeholders
+            .iter()
+            .any(|placeholder| USER_VARIABLES.contains(&placeholder.name))
+    })
+}
+
+/// Replace the server-side variables in `template`. Values supplied by the
+/// user (their claims) are URL encoded, while the rest are inserted as they
+/// are, so that e.g. a configured base URL or a date format containing `/`
+/// can form part of a path.
+pub fn substitute(template: &str, variables: &Variables) -> Result<String, TemplateError> {
+    let placeholders = placeholders(template)?;
+
+    if placeholders.is_empty() {
+        return Ok(template.to_string());
+    }
+    let mut rendered = String::with_capacity(template.len());
+    let mut offset = 0;
+
+    for placeholder in placeholders {
+        rendered.push_str(&template[offset..placeholder.start]);
+        offset = placeholder.end;
+
+        let user = |claim: Option<&str>| encode(claim.unwrap_or_default()).into_owned();
+        let value = match placeholder.name {
+            "user.login" => user(variables.user.and_then(|u| u.username.as_deref())),
+            "user.email" => user(variables.user.and_then(|u| u.email.as_deref())),
+            "user.subject" => user(variables.user.map(|u| u.subject.as_str())),
+            "user.issuer" => user(variables.user.map(|u| u.issuer.as_str())),
+            "date" => format_time(
+                variables.now,
+                placeholder.name,
+                placeholder.format.unwrap_or(DEFAULT_DATE_FORMAT),
+            )?,
+            "time" => format_time(
+                variables.now,
+                placeholder.name,
+                placeholder.format.unwrap_or(DEFAULT_TIME_FORMAT),
+            )?,
+            "timestamp" => variables.now.timestamp().to_string(),
+            "host" => variables.host.to_string(),
+            name => name
+                .strip_prefix("config.")
+                .and_then(|key| variables.config.iter().find(|v| v.name == key))
+                .map(|variable| variable.value.clone())
+                .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?,
+        };
+        // Keep values from being interpreted as parameter placeholders
+        rendered.push_str(
+            &value
+                .replace('{', "%7B")
+                .replace('}', "%7D")
+                .replace('^', "%5E"),
+        );
+    }
+    rendered.push_str(&template[offset..]);
+    Ok(rendered)
+}
+
+fn format_time(now: DateTime<Utc>, name: &str, format: &str) -> Result<String, TemplateError> {
+    let mut formatted = String::new();
+
+    write!(formatted, "{}", now.format(format))
+        .map_err(|_| TemplateError::InvalidFormat(name.to_string(), format.to_string()))?;
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn config() -> Vec<TemplateVariable> {
+        vec![TemplateVariable {
+            name: "wiki".to_string(),
+            value: "https://wiki.example.com".to_string(),
+        }]
+    }
+
+    #[test]
+    fn it_only_accepts_web_pages() {
+        let config = config();
+
+        for template in [
+            "https://example.com",
+            "HTTP://example.com",
+            "{$config.wiki}/Oncall",
+        ] {
+            assert_eq!(validate_scheme(template, &config), Ok(()), "{}", template);
+        }
+        for template in [
+            "javascript:alert(1)",
+            " https://example.com",
+            "data:text/html,<script>alert(1)</script>",
+            "//example.com",
+            "{}",
+            "{$date:javascript:alert(1)}",
+            "{$user.login}https://example.com",
+        ] {
+            assert!(validate_scheme(template, &config).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn it_rejects_unknown_variables_and_invalid_formats() {
+        let config = config();
+
+        assert_eq!(
+            validate("https://github.com/{$user.login}/dotfiles", &config),
+            Ok(())
+        );
+        assert_eq!(
+            validate("{$config.wiki}/Oncall/{$date:%Y-%m}", &config),
+            Ok(())
+        );
+        assert_eq!(
+            validate("https://example.com/{$user.name}", &config),
+            Err(TemplateError::UnknownVariable("user.name".to_string()))
+        );
+        assert_eq!(
+            validate("{$config.jira}/browse", &config),
+            Err(TemplateError::UnknownVariable("config.jira".to_string()))
+        );
+        assert_eq!(
+            validate("https://calendar/{$date:%Q}", &config),
+            Err(TemplateError::InvalidFormat(
+                "date".to_string(),
+                "%Q".to_string()
+            ))
+        );
+        assert_eq!(
+            validate("https://calendar/{$date", &config),
+            Err(TemplateError::Unclosed(17))
+        );
+    }
+
+    #[test]
+    fn it_substitutes_variables() {
+        let config = config();
+        let user = Identity {
+            issuer: "https://accounts.example.com".to_string(),
+            subject: "1234".to_string(),
+            username: Some("ada lovelace".to_string()),
+            email: None,
+            groups: vec![],
+        };
+        let variables = Variables {
+            user: Some(&user),
+            now: DateTime::parse_from_rfc3339("2026-10-18T09:30:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            host: "go.example.com",
+            config: &config,
+        };
+
+        assert_eq!(
+            substitute("https://github.com/{$user.login}/dotfiles", &variables),
+            Ok("https://github.com/ada%20lovelace/dotfiles".to_string())
+        );
+        assert_eq!(
+            substitute(
+                "{$config.wiki}/standup/{$date:%Y/%m/%d}?at={$time}&from={$host}",
+                &variables
+            ),
+            Ok(
+                "https://wiki.example.com/standup/2026/10/18?at=09:30&from=go.example.com"
+                    .to_string()
+            )
+        );
+        // Parameter placeholders are left for `render_url_template`
+        assert_eq!(
+            substitute("https://search/{$date}?q={^}", &variables),
+            Ok("https://search/2026-10-18?q={^}".to_string())
+        );
+    }
+}
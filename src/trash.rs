@@ -0,0 +1,163 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    oidc::identity::Identity,
+    rustlink::Rustlink,
+    store::{Condition, Operation, Store},
+    util::{self, AliasNormalization, TRASH_NAMESPACE},
+    RustlinkAlias,
+};
+
+/// How long deleted links are kept unless configured otherwise
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// How often the leader purges links which have been in the trash for longer
+/// than the retention period
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A deleted link, kept in the trash until it's restored or purged
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TrashedRustlink {
+    pub rustlink: Rustlink,
+    pub deleted_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
+}
+
+impl TrashedRustlink {
+    pub fn new(mut rustlink: Rustlink, identity: Option<&Identity>, now: DateTime<Utc>) -> Self {
+        rustlink.revision = None;
+
+        TrashedRustlink {
+            rustlink,
+            deleted_at: now,
+            deleted_by: identity.map(Identity::owner),
+        }
+    }
+
+    /// When the link is purged, if it isn't restored before then
+    pub fn purge_at(&self, retention: chrono::Duration) -> DateTime<Utc> {
+        self.deleted_at + retention
+    }
+}
+
+/// The links in the trash by canonical alias, as synced by the
+/// [`crate::worker::Worker`]
+pub type Trash = HashMap<RustlinkAlias, TrashedRustlink>;
+
+/// Apply a deleted link stored at a key with the given alias (as spelled in
+/// the key)
+pub fn apply_put(
+    trash: &mut Trash,
+    key_alias: &str,
+    value: &[u8],
+    normalizations: &[AliasNormalization],
+) -> Result<(), serde_json::Error> {
+    let trashed = serde_json::from_slice::<TrashedRustlink>(value)?;
+    trash.insert(util::canonicalize_alias(key_alias, normalizations), trashed);
+    Ok(())
+}
+
+/// Apply the restoration (or purge) of a deleted link stored at a key with the
+/// given alias
+pub fn apply_delete(trash: &mut Trash, key_alias: &str, normalizations: &[AliasNormalization]) {
+    trash.remove(&util::canonicalize_alias(key_alias, normalizations));
+}
+
+/// Permanently delete links which have been in the trash for longer than
+/// `retention`, returning how many were purged. Each is only deleted if it
+/// hasn't changed since it was read, so links trashed again in the meantime
+/// are kept.
+pub async fn purge(
+    store: &dyn Store,
+    retention: chrono::Duration,
+    now: DateTime<Utc>,
+) -> Result<usize, etcd_rs::Error> {
+    let (values, _) = store.get_prefix(TRASH_NAMESPACE).await?;
+    let mut purged = 0;
+
+    for value in values {
+        let expired = match serde_json::from_slice::<TrashedRustlink>(&value.value) {
+            Ok(trashed) => trashed.purge_at(retention) <= now,
+            Err(e) => {
+                eprintln!("Failed to parse trashed link at {}: {:?}", value.key, e);
+                false
+            }
+        };
+
+        if expired {
+            let deleted = store
+                .txn(
+                    vec![Condition::ModRevision(
+                        value.key.clone(),
+                        value.mod_revision,
+                    )],
+                    vec![Operation::Delete(value.key)],
+                )
+                .await?;
+            purged += deleted.is_some() as usize;
+        }
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::store::memory::InMemoryStore;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp).unwrap().into()
+    }
+
+    #[actix_web::test]
+    async fn it_purges_links_trashed_for_longer_than_the_retention_period() {
+        let store = InMemoryStore::default();
+
+        for (alias, deleted_at) in [
+            ("standup", "2024-01-01T00:00:00Z"),
+            ("retro", "2024-01-25T00:00:00Z"),
+        ] {
+            let trashed = TrashedRustlink::new(Rustlink::default(), None, at(deleted_at));
+            store
+                .put(
+                    util::alias_to_trash_key(alias, &[]),
+                    serde_json::to_vec(&trashed).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let purged = purge(
+            &store,
+            chrono::Duration::days(30),
+            at("2024-02-15T00:00:00Z"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(purged, 1);
+
+        let mut trash = Trash::new();
+        for value in store.get_prefix(TRASH_NAMESPACE).await.unwrap().0 {
+            let alias = value.key.strip_prefix(TRASH_NAMESPACE).unwrap();
+            apply_put(&mut trash, alias, &value.value, &[]).unwrap();
+        }
+        assert!(!trash.contains_key("standup"));
+        assert_eq!(trash["retro"].deleted_at, at("2024-01-25T00:00:00Z"));
+    }
+
+    #[test]
+    fn it_applies_trashed_links_by_canonical_alias() {
+        let mut trash = Trash::new();
+        let trashed = TrashedRustlink::new(Rustlink::default(), None, at("2024-01-01T00:00:00Z"));
+        let value = serde_json::to_vec(&trashed).unwrap();
+
+        apply_put(&mut trash, "OnCall", &value, &[AliasNormalization::Case]).unwrap();
+        assert_eq!(trash.get("oncall"), Some(&trashed));
+
+        apply_delete(&mut trash, "ONCALL", &[AliasNormalization::Case]);
+        assert!(trash.is_empty());
+    }
+}
@@ -1,8 +1,17 @@
 import { OIDCProvider } from "src/auth"
+import { NotFoundProps } from "src/pages/404"
+import { BundleProps } from "src/pages/Bundle"
+import { PreviewProps } from "src/pages/Preview"
+
+export type AppContext = {
+    not_found?: NotFoundProps
+    bundle?: BundleProps
+    preview?: PreviewProps
+}
 
 export type AppProps = {
     location: any,
-    context: any,
+    context: AppContext,
     oidc_providers: OIDCProvider[],
     oauth_redirect_endpoint: string,
     login_path: string
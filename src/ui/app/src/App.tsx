@@ -7,13 +7,15 @@ import favicon from '../public/favicon.png'
 
 import Home from './pages/Home'
 import NotFound from './pages/404'
+import Bundle from './pages/Bundle'
+import Preview from './pages/Preview'
 import Login from './pages/Login'
 import { AppProps } from './@types'
 
 import { OidcClient, OidcClientSettingsStore } from 'oidc-client-ts'
 import { NamedOidcClient } from './auth'
 
-const App: React.FC<Partial<AppProps>> = ({ login_path, oidc_providers, oauth_redirect_endpoint, ...props }) => {
+const App: React.FC<Partial<AppProps>> = ({ login_path, oidc_providers, oauth_redirect_endpoint, context, ...props }) => {
    console.log('found props: ', props)
 
    const oidc_clients: NamedOidcClient[] = oidc_providers?.map(provider => {
@@ -25,6 +27,18 @@ const App: React.FC<Partial<AppProps>> = ({ login_path, oidc_providers, oauth_re
       return <Login oidc_clients={oidc_clients} />
    }
 
+   const NotFoundComponent = () => {
+      return <NotFound {...context?.not_found} />
+   }
+
+   const BundleComponent = () => {
+      return <Bundle {...context?.bundle} />
+   }
+
+   const PreviewComponent = () => {
+      return <Preview {...context?.preview} />
+   }
+
    return (
       <>
          <Helmet>
@@ -35,7 +49,9 @@ const App: React.FC<Partial<AppProps>> = ({ login_path, oidc_providers, oauth_re
          <Switch>
             <Route exact path='/' component={Home} />
             <Route path='/login' component={LoginComponent} />
-            <Route path='*' component={NotFound} />
+            {context?.preview && <Route path='*' component={PreviewComponent} />}
+            {context?.bundle && <Route path='*' component={BundleComponent} />}
+            <Route path='*' component={NotFoundComponent} />
          </Switch>
       </>
    )
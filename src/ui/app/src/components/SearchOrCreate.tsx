@@ -1,4 +1,4 @@
-import React, { Fragment, useState } from "react"
+import React, { Fragment, useEffect, useState } from "react"
 import { Combobox, Transition } from '@headlessui/react'
 
 import './SearchOrCreate.scss'
@@ -6,45 +6,62 @@ import './SearchOrCreate.scss'
 type Rustlink = {
     alias: string,
     url: string,
+    description?: string,
+    deprecated?: boolean,
 }
 
-type Person = {
-    id: number,
-    name: string,
+type Option = {
+    alias: string,
+    rustlink?: Rustlink,
 }
 
-const people: Person[] = [
-    { id: 1, name: 'Wade Cooper' },
-    { id: 2, name: 'Arlene Mccoy' },
-    { id: 3, name: 'Devon Webb' },
-    { id: 4, name: 'Tom Cook' },
-    { id: 5, name: 'Tanya Fox' },
-    { id: 6, name: 'Hellen Schmidt' },
-]
-
+const aliasPath = (alias: string) =>
+    alias.split('/').map(encodeURIComponent).join('/')
 
 export default function SearchOrCreate() {
-    const [selected, setSelected] = useState(people[0])
     const [query, setQuery] = useState('')
+    const [results, setResults] = useState<Rustlink[]>([])
+
+    useEffect(() => {
+        if (query.trim() === '') {
+            setResults([])
+            return
+        }
+        // Drop responses to queries which have since been typed over
+        const controller = new AbortController()
+
+        fetch(`/api/v1/search?q=${encodeURIComponent(query)}`, { signal: controller.signal })
+            .then((response) => response.ok ? response.json() : [])
+            .then(setResults)
+            .catch(() => {})
 
-    const filteredPeople =
-        query === ''
-            ? people
-            : people.filter((person) =>
-                person.name
-                    .toLowerCase()
-                    .replace(/\s+/g, '')
-                    .includes(query.toLowerCase().replace(/\s+/g, ''))
-            )
+        return () => controller.abort()
+    }, [query])
+
+    const alias = query.trim()
+    const options: Option[] = results.map((rustlink) => ({ alias: rustlink.alias, rustlink }))
+
+    // Offer to create the link when nothing matches the query exactly, which
+    // is handled by the not found page
+    if (alias !== '' && !results.some((rustlink) => rustlink.alias === alias)) {
+        options.push({ alias })
+    }
+
+    const open = (option: Option | null) => {
+        if (option) {
+            window.location.href = `/${aliasPath(option.alias)}`
+        }
+    }
 
     return (
         <div className="fixed top-16 w-72">
-            <Combobox value={selected} onChange={setSelected}>
+            <Combobox value={null as Option | null} onChange={open}>
                 <div className="relative mt-1">
                     <div className="relative w-full cursor-default overflow-hidden rounded-lg bg-white text-left shadow-md focus:outline-none focus-visible:ring-2 focus-visible:ring-white/75 focus-visible:ring-offset-2 focus-visible:ring-offset-teal-300 sm:text-sm">
                         <Combobox.Input
                             className="w-full border-none py-2 pl-3 pr-10 text-sm leading-5 text-gray-900 focus:ring-0"
-                            displayValue={(person: Person) => person.name}
+                            placeholder="Search or create a link"
+                            displayValue={() => query}
                             onChange={(event) => setQuery(event.target.value)}
                         />
                         <Combobox.Button className="absolute inset-y-0 right-0 flex items-center pr-2">
@@ -55,47 +72,38 @@ export default function SearchOrCreate() {
                         leave="transition ease-in duration-100"
                         leaveFrom="opacity-100"
                         leaveTo="opacity-0"
-                        afterLeave={() => setQuery('')}
                     >
                         <Combobox.Options className="absolute mt-1 max-h-60 w-full overflow-auto rounded-md bg-white py-1 text-base shadow-lg ring-1 ring-black/5 focus:outline-none sm:text-sm">
-                            {filteredPeople.length === 0 && query !== '' ? (
-                                <div className="relative cursor-default select-none py-2 px-4 text-gray-700">
-                                    Nothing found.
-                                </div>
-                            ) : (
-                                filteredPeople.map((person) => (
-                                    <Combobox.Option
-                                        key={person.id}
-                                        className={({ active }) =>
-                                            `relative cursor-default select-none py-2 pl-10 pr-4 ${active ? 'bg-teal-600 text-white' : 'text-gray-900'
-                                            }`
-                                        }
-                                        value={person}
-                                    >
-                                        {({ selected, active }) => (
-                                            <>
-                                                <span
-                                                    className={`block truncate ${selected ? 'font-medium' : 'font-normal'
-                                                        }`}
-                                                >
-                                                    {person.name}
-                                                </span>
-                                                {selected ? (
-                                                    <span
-                                                        className={`absolute inset-y-0 left-0 flex items-center pl-3 ${active ? 'text-white' : 'text-teal-600'
-                                                            }`}
-                                                    >
-                                                    </span>
-                                                ) : null}
-                                            </>
-                                        )}
-                                    </Combobox.Option>
-                                ))
-                            )}
+                            {options.map((option) => (
+                                <Combobox.Option
+                                    key={`${option.rustlink ? 'link' : 'create'}:${option.alias}`}
+                                    className={({ active }) =>
+                                        `relative cursor-default select-none py-2 pl-10 pr-4 ${active ? 'bg-teal-600 text-white' : 'text-gray-900'
+                                        }`
+                                    }
+                                    value={option}
+                                >
+                                    {option.rustlink ? (
+                                        <>
+                                            <span className="block truncate font-medium">
+                                                go/{option.alias}
+                                                {option.rustlink.deprecated && ' (deprecated)'}
+                                            </span>
+                                            <span className="block truncate">
+                                                {option.rustlink.description || option.rustlink.url}
+                                            </span>
+                                        </>
+                                    ) : (
+                                        <span className="block truncate">
+                                            Create go/<strong>{option.alias}</strong>
+                                        </span>
+                                    )}
+                                </Combobox.Option>
+                            ))}
                         </Combobox.Options>
                     </Transition>
                 </div>
             </Combobox>
         </div>
     )
-}
\ No newline at end of file
+}
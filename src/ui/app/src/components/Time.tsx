@@ -0,0 +1,19 @@
+import React, { useEffect, useState } from 'react'
+
+export type TimeProps = {
+   at: string
+}
+
+// Rendered as the timestamp itself on the server, and formatted for the
+// viewer's locale and timezone only once hydrated, so that both renders match
+const Time: React.FC<TimeProps> = ({ at }) => {
+   const [formatted, setFormatted] = useState(at)
+
+   useEffect(() => {
+      setFormatted(new Date(at).toLocaleString())
+   }, [at])
+
+   return <time dateTime={at}>{formatted}</time>
+}
+
+export default Time
@@ -1,13 +1,128 @@
-import React from 'react'
+import React, { useState } from 'react'
+import Time from '../components/Time'
 
-const NotFound: React.FC = () => {
-   return (
-      <>
+export type Suggestion = {
+   alias: string
+   url: string
+}
+
+export type Deleted = {
+   deleted_at: string
+   deleted_by?: string
+   purge_at: string
+}
+
+export type NotFoundProps = {
+   alias?: string
+   suggestions?: Suggestion[]
+   deleted?: Deleted
+}
+
+const aliasPath = (alias: string) =>
+   alias.split('/').map(encodeURIComponent).join('/')
+
+const NotFound: React.FC<NotFoundProps> = ({
+   alias,
+   suggestions = [],
+   deleted,
+}) => {
+   const [url, setUrl] = useState('')
+   const [error, setError] = useState<string | undefined>()
+
+   const create = async (event: React.FormEvent) => {
+      event.preventDefault()
+
+      if (!alias) {
+         return
+      }
+      const response = await fetch(`/api/v1/links/${aliasPath(alias)}`, {
+         method: 'PUT',
+         headers: { 'Content-Type': 'application/json' },
+         body: JSON.stringify({ url }),
+      })
+
+      if (response.ok) {
+         window.location.href = `/${aliasPath(alias)}`
+      } else {
+         setError(await response.text())
+      }
+   }
+
+   const restore = async () => {
+      if (!alias) {
+         return
+      }
+      const response = await fetch(
+         `/api/v1/trash/${aliasPath(alias)}/restore`,
+         { method: 'POST' }
+      )
+
+      if (response.ok) {
+         window.location.href = `/${aliasPath(alias)}`
+      } else {
+         setError(await response.text())
+      }
+   }
+
+   if (!alias) {
+      return (
          <div className='wrapper'>
             <h1>404 Error</h1>
             <p>Something went wrong...</p>
          </div>
-      </>
+      )
+   }
+
+   return (
+      <div className='wrapper'>
+         {deleted ? (
+            <>
+               <h1>
+                  go/<strong>{alias}</strong> was deleted
+                  {deleted.deleted_by && <> by {deleted.deleted_by}</>}
+               </h1>
+               <p>
+                  Deleted <Time at={deleted.deleted_at} />, it can be restored
+                  until <Time at={deleted.purge_at} />.
+               </p>
+               <button type='button' onClick={restore}>
+                  Restore go/{alias}
+               </button>
+            </>
+         ) : (
+            <h1>
+               go/<strong>{alias}</strong> doesn't exist
+            </h1>
+         )}
+         {suggestions.length > 0 && (
+            <>
+               <p>Did you mean:</p>
+               <ul>
+                  {suggestions.map((suggestion) => (
+                     <li key={suggestion.alias}>
+                        <a href={`/${aliasPath(suggestion.alias)}`}>
+                           go/{suggestion.alias}
+                        </a>{' '}
+                        <span className='text-gray-500'>{suggestion.url}</span>
+                     </li>
+                  ))}
+               </ul>
+            </>
+         )}
+         <form onSubmit={create}>
+            <label htmlFor='create-url'>Create go/{alias} pointing to:</label>
+            <input
+               id='create-url'
+               type='url'
+               required
+               placeholder='https://'
+               value={url}
+               onChange={(event) => setUrl(event.target.value)}
+            />
+            <button type='submit'>Create go/{alias}</button>
+            {error && <p className='text-red-600'>{error}</p>}
+         </form>
+      </div>
    )
 }
 
@@ -0,0 +1,40 @@
+import React from 'react'
+
+export type BundleProps = {
+   alias?: string
+   urls?: string[]
+}
+
+const Bundle: React.FC<BundleProps> = ({ alias, urls = [] }) => {
+   const openAll = () => {
+      // Open in reverse so tabs end up in the bundle's order, with the first
+      // link focused last
+      urls
+         .slice(1)
+         .reverse()
+         .forEach((url) => window.open(url, '_blank', 'noopener'))
+      window.location.href = urls[0]
+   }
+
+   return (
+      <div className='wrapper'>
+         <h1>
+            go/<strong>{alias}</strong>
+         </h1>
+         <button type='button' onClick={openAll} disabled={urls.length === 0}>
+            Open all ({urls.length})
+         </button>
+         <ul>
+            {urls.map((url, index) => (
+               <li key={index}>
+                  <a href={url} target='_blank' rel='noopener noreferrer'>
+                     {url}
+                  </a>
+               </li>
+            ))}
+         </ul>
+      </div>
+   )
+}
+
+export default Bundle
@@ -0,0 +1,118 @@
+import React from 'react'
+import Time from '../components/Time'
+
+export type PreviewProps = {
+   alias?: string
+   target_alias?: string
+   urls?: string[]
+   description?: string
+   owners?: string[]
+   tags?: string[]
+   created_at?: string
+   created_by?: string
+   updated_at?: string
+   updated_by?: string
+   archived?: boolean
+   deprecated?: boolean
+   revision?: number
+   usage?: number
+   redirect?: string
+}
+
+const aliasPath = (alias: string) =>
+   alias.split('/').map(encodeURIComponent).join('/')
+
+const changed = (at?: string, by?: string) => (
+   <>
+      {at && <Time at={at} />}
+      {at && by && ' '}
+      {by && `by ${by}`}
+   </>
+)
+
+const Preview: React.FC<PreviewProps> = ({
+   alias,
+   target_alias,
+   urls = [],
+   description,
+   owners = [],
+   tags = [],
+   created_at,
+   created_by,
+   updated_at,
+   updated_by,
+   archived = false,
+   deprecated = false,
+   revision,
+   usage,
+   redirect,
+}) => {
+   return (
+      <div className='wrapper'>
+         <h1>
+            go/<strong>{alias}</strong>
+         </h1>
+         {archived && <p>This link is archived</p>}
+         {deprecated && target_alias ? (
+            <p>
+               This alias is deprecated, use <a href={`/${aliasPath(target_alias)}`}>go/{target_alias}</a> instead
+            </p>
+         ) : (
+            target_alias && <p>Refers to go/{target_alias}</p>
+         )}
+         {description && <p>{description}</p>}
+         <dl>
+            <dt>Goes to</dt>
+            {urls.map((url, index) => (
+               <dd key={index}>
+                  <a href={url}>{url}</a>
+               </dd>
+            ))}
+            {owners.length > 0 && (
+               <>
+                  <dt>Owners</dt>
+                  <dd>{owners.join(', ')}</dd>
+               </>
+            )}
+            {tags.length > 0 && (
+               <>
+                  <dt>Tags</dt>
+                  <dd>{tags.join(', ')}</dd>
+               </>
+            )}
+            {(created_at || created_by) && (
+               <>
+                  <dt>Created</dt>
+                  <dd>{changed(created_at, created_by)}</dd>
+               </>
+            )}
+            {(updated_at || updated_by) && (
+               <>
+                  <dt>Updated</dt>
+                  <dd>{changed(updated_at, updated_by)}</dd>
+               </>
+            )}
+            {revision !== undefined && (
+               <>
+                  <dt>Last modified at revision</dt>
+                  <dd>{revision}</dd>
+               </>
+            )}
+            {usage !== undefined && (
+               <>
+                  <dt>Used</dt>
+                  <dd>{usage} times</dd>
+               </>
+            )}
+            {redirect && (
+               <>
+                  <dt>Redirect</dt>
+                  <dd>{redirect}</dd>
+               </>
+            )}
+         </dl>
+      </div>
+   )
+}
+
+export default Preview
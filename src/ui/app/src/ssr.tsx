@@ -19,7 +19,8 @@ export const Index = (params: string | undefined): string => {
    
    ${helmetData.title.toString()}
    ${helmetData.meta.toString()}
-   <link rel="stylesheet" href="./_ui/styles/ssr.css">
+   <link rel="stylesheet" href="/_ui/styles/ssr.css">
+   <link rel="search" type="application/opensearchdescription+xml" title="rustlinks" href="/api/v1/opensearch/description.xml">
 </head>
 <body>
    <noscript>Your browser does not support JavaScript!</noscript>
@@ -36,11 +37,11 @@ export const Index = (params: string | undefined): string => {
    <div id="root">
          ${renderToString(
       <StaticRouter {...props}>
-         <App />
+         <App {...props} />
       </StaticRouter>
    )}
    </div>
-   <script src="./_ui/scripts/bundle.js"></script>
+   <script src="/_ui/scripts/bundle.js"></script>
 </body>
 </html>
     `
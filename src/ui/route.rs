@@ -4,29 +4,122 @@ use actix_web::{
     web::{self, Bytes},
     Error, HttpRequest, HttpResponse, Responder,
 };
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
 use ssr_rs::Ssr;
 
-use crate::state::AppState;
+use crate::{rustlink::RedirectMode, state::AppState, trash::TrashedRustlink};
+
+/// An existing alias offered in place of one which couldn't be found
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub alias: String,
+    pub url: String,
+}
+
+/// Where a link goes, and what's known about it, shown instead of following it
+#[derive(Debug, Serialize)]
+pub struct Preview {
+    pub alias: String,
+    /// The alias the link refers to, if it refers to another
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_alias: Option<String>,
+    /// Where the link would currently send the user (several for bundles)
+    pub urls: Vec<String>,
+    pub description: Option<String>,
+    pub owners: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+    pub archived: bool,
+    pub deprecated: bool,
+    pub revision: Option<i64>,
+    /// Redirects served by this node, where they're tracked
+    pub usage: Option<u64>,
+    pub redirect: RedirectMode,
+}
 
 #[get("*")]
 pub async fn index(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    render(&req, &data, json!({}), StatusCode::OK).await
+}
+
+/// Render the page shown for an alias which doesn't exist, listing similar
+/// aliases and offering to create it
+pub async fn not_found(
+    req: &HttpRequest,
+    data: &AppState,
+    alias: &str,
+    suggestions: Vec<Suggestion>,
+    deleted: Option<&TrashedRustlink>,
+) -> HttpResponse {
+    let context = json!({
+        "not_found": {
+            "alias": alias,
+            "suggestions": suggestions,
+            "deleted": deleted.map(|trashed| json!({
+                "deleted_at": trashed.deleted_at,
+                "deleted_by": trashed.deleted_by,
+                "purge_at": trashed.purge_at(data.trash_retention),
+            })),
+        }
+    });
+    let status = match deleted {
+        Some(_) => StatusCode::GONE,
+        None => StatusCode::NOT_FOUND,
+    };
+    render(req, data, context, status).await
+}
+
+/// Render the page listing the URLs of a bundle link, offering to open them
+/// all at once
+pub async fn bundle(
+    req: &HttpRequest,
+    data: &AppState,
+    alias: &str,
+    urls: &[String],
+) -> HttpResponse {
+    let context = json!({
+        "bundle": {
+            "alias": alias,
+            "urls": urls,
+        }
+    });
+    render(req, data, context, StatusCode::OK).await
+}
+
+/// Render the page previewing a link
+pub async fn preview(req: &HttpRequest, data: &AppState, preview: &Preview) -> HttpResponse {
+    let context = json!({ "preview": preview });
+    render(req, data, context, StatusCode::OK).await
+}
+
+/// Server-side render the UI, passing `context` through to the page
+pub async fn render(
+    req: &HttpRequest,
+    data: &AppState,
+    context: serde_json::Value,
+    status: StatusCode,
+) -> HttpResponse {
     let oidc_providers: Vec<crate::oidc::provider::OIDCProvider> =
         data.oidc_providers.read().await.clone();
-    let oidc_providers_string = serde_json::to_string(&oidc_providers).unwrap_or("[]".to_string());
-
-    let props = format!(
-        r##"{{
-            "location": "{}",
-            "context": {{}},
-            "oidc_providers": {},
-            "oauth_redirect_endpoint": "{}",
-            "login_path": "{}"
-        }}"##,
-        req.uri(),
-        oidc_providers_string,
-        data.oauth_redirect_endpoint,
-        data.login_path,
-    );
+
+    let props = json!({
+        "location": req.uri().to_string(),
+        "context": context,
+        "oidc_providers": serde_json::to_value(&oidc_providers).unwrap_or(json!([])),
+        "oauth_redirect_endpoint": data.oauth_redirect_endpoint,
+        "login_path": data.login_path,
+    })
+    .to_string();
 
     let source = data.js_source.read().await;
     let js: Ssr<'_> = ssr_rs::Ssr::new(source.to_string(), "SSR"); // TODO: figure out how to debug SSR errors better
@@ -35,7 +128,7 @@ pub async fn index(req: HttpRequest, data: web::Data<AppState>) -> impl Responde
     let bytes = Bytes::from(response_body);
     let body: tokio_stream::Once<Result<Bytes, Error>> = tokio_stream::once(Ok(bytes));
 
-    HttpResponse::build(StatusCode::OK)
+    HttpResponse::build(status)
         .content_type("text/html; charset=utf-8")
         .streaming(body)
 }
@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use dashmap::DashMap;
+
+use crate::RustlinkAlias;
+
+/// Redirects served by this node per canonical alias. Counters are atomic so
+/// that redirects never wait on each other, or on the listings reading them.
+#[derive(Debug, Default)]
+pub struct Usage {
+    counts: DashMap<RustlinkAlias, AtomicU64>,
+}
+
+impl Usage {
+    /// Count a redirect to `alias`
+    pub fn record(&self, alias: &str) {
+        // Only new aliases need a write lock on their shard
+        if let Some(count) = self.counts.get(alias) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.counts
+            .entry(alias.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Redirects to `alias` counted so far
+    pub fn get(&self, alias: &str) -> u64 {
+        self.counts
+            .get(alias)
+            .map(|count| count.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    /// Forget the usage of a deleted link
+    pub fn remove(&self, alias: &str) {
+        self.counts.remove(alias);
+    }
+
+    /// Carry the usage of `from` over to `to`, which a link was renamed to
+    pub fn rename(&self, from: &str, to: &str) {
+        if let Some((_, count)) = self.counts.remove(from) {
+            self.counts
+                .entry(to.to_string())
+                .or_default()
+                .fetch_add(count.into_inner(), Ordering::Relaxed);
+        }
+    }
+
+    /// Add previously counted usage, e.g. restored from disk
+    pub fn extend(&self, usage: impl IntoIterator<Item = (RustlinkAlias, u64)>) {
+        for (alias, used) in usage {
+            self.counts
+                .entry(alias)
+                .or_default()
+                .fetch_add(used, Ordering::Relaxed);
+        }
+    }
+
+    /// The usage of every alias, as of now
+    pub fn snapshot(&self) -> HashMap<RustlinkAlias, u64> {
+        self.counts
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl From<HashMap<RustlinkAlias, u64>> for Usage {
+    fn from(usage: HashMap<RustlinkAlias, u64>) -> Self {
+        let counted = Usage::default();
+        counted.extend(usage);
+        counted
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_usage_and_carries_it_over_renames() {
+        let usage = Usage::from(HashMap::from([("oncall".to_string(), 3)]));
+        usage.record("oncall");
+        usage.record("standup");
+        assert_eq!(usage.get("oncall"), 4);
+
+        usage.rename("oncall", "pager");
+        assert_eq!(usage.get("oncall"), 0);
+        assert_eq!(usage.get("pager"), 4);
+
+        usage.remove("standup");
+        assert_eq!(usage.snapshot(), HashMap::from([("pager".to_string(), 4)]));
+    }
+}
@@ -1,17 +1,213 @@
+use clap::ValueEnum;
 use dialoguer::Password;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use urlencoding::encode;
 
 pub const NAMESPACE: &str = "rustlinks/";
 
+/// Personal links are kept outside of [`NAMESPACE`], so that nodes watching the
+/// shared links don't also receive every user's personal ones
+pub const PERSONAL_NAMESPACE: &str = "rustlinks-personal/";
+
+/// Collections are kept outside of [`NAMESPACE`] too, so that they're never
+/// mistaken for links
+pub const COLLECTIONS_NAMESPACE: &str = "rustlinks-collections/";
+
+/// Deleted links are moved here, until they're restored or purged
+pub const TRASH_NAMESPACE: &str = "rustlinks-trash/";
+
+/// Path prefixes which resolve against the requesting user's personal links
+pub const PERSONAL_PREFIXES: [&str; 2] = ["~/", "me/"];
+
+/// Steps applied to aliases to produce their canonical form, which is what
+/// links are stored and looked up by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum AliasNormalization {
+    /// Unicode NFKC normalization (e.g. `ｏｎｃａｌｌ` => `oncall`)
+    Nfkc,
+    /// Case-insensitive matching (e.g. `OnCall` => `oncall`)
+    Case,
+    /// Ignore dashes and underscores (e.g. `on-call` => `oncall`)
+    Separators,
+}
+
 pub fn key_to_alias(key: &str) -> String {
     let mut split = key.split('/');
     split.next();
     split.remainder().unwrap().to_string()
 }
 
-pub fn alias_to_key(alias: &str) -> String {
-    format!("{}{}", NAMESPACE, alias)
+pub fn alias_to_key(alias: &str, normalizations: &[AliasNormalization]) -> String {
+    format!("{}{}", NAMESPACE, canonicalize_alias(alias, normalizations))
+}
+
+/// The key the collection named `name` is stored under, which is canonicalized
+/// the same way as aliases
+pub fn collection_to_key(name: &str, normalizations: &[AliasNormalization]) -> String {
+    format!(
+        "{}{}",
+        COLLECTIONS_NAMESPACE,
+        canonicalize_alias(name, normalizations)
+    )
+}
+
+/// The key the deleted link at `alias` is kept under
+pub fn alias_to_trash_key(alias: &str, normalizations: &[AliasNormalization]) -> String {
+    format!(
+        "{}{}",
+        TRASH_NAMESPACE,
+        canonicalize_alias(alias, normalizations)
+    )
+}
+
+/// The prefix under which `owner`'s personal links are stored
+pub fn personal_prefix(owner: &str) -> String {
+    format!("{}{}/", PERSONAL_NAMESPACE, encode(owner))
+}
+
+pub fn personal_alias_to_key(
+    owner: &str,
+    alias: &str,
+    normalizations: &[AliasNormalization],
+) -> String {
+    format!(
+        "{}{}",
+        personal_prefix(owner),
+        canonicalize_alias(alias, normalizations)
+    )
+}
+
+/// The path following a personal prefix (e.g. `foo` for `~/foo`), if `path`
+/// starts with one
+pub fn strip_personal_prefix(path: &str) -> Option<&str> {
+    PERSONAL_PREFIXES
+        .iter()
+        .find_map(|prefix| path.strip_prefix(prefix))
+}
+
+/// Canonicalize an alias (or a path starting with one), applying each
+/// configured normalization to every `/`-separated segment.
+pub fn canonicalize_alias(alias: &str, normalizations: &[AliasNormalization]) -> String {
+    if normalizations.is_empty() {
+        return alias.to_string();
+    }
+
+    alias
+        .split('/')
+        .map(|segment| {
+            let mut segment = segment.to_string();
+
+            // NFKC goes first, so that the other steps see composed characters
+            if normalizations.contains(&AliasNormalization::Nfkc) {
+                segment = nfkc_within_segment(&segment);
+            }
+            if normalizations.contains(&AliasNormalization::Case) {
+                segment = segment.to_lowercase();
+            }
+            if normalizations.contains(&AliasNormalization::Separators) {
+                segment.retain(|c| c != '-' && c != '_');
+            }
+            segment
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// NFKC normalize a path segment, except for characters which would become
+/// (or contain) a `/` (e.g. `／` or `℅`), which are kept as they are so that
+/// canonicalization never changes how many segments a path has
+fn nfkc_within_segment(segment: &str) -> String {
+    let mut normalized = String::with_capacity(segment.len());
+    let mut run = String::new();
+
+    for c in segment.chars() {
+        if std::iter::once(c).nfkc().any(|n| n == '/') {
+            normalized.extend(run.nfkc());
+            run.clear();
+            normalized.push(c);
+        } else {
+            run.push(c);
+        }
+    }
+    normalized.extend(run.nfkc());
+    normalized
 }
 
 pub fn password_prompt(prompt: &str) -> Result<String, dialoguer::Error> {
     Password::new().with_prompt(prompt).interact()
 }
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn it_strips_personal_prefixes() {
+        assert_eq!(strip_personal_prefix("~/standup"), Some("standup"));
+        assert_eq!(
+            strip_personal_prefix("me/standup/notes"),
+            Some("standup/notes")
+        );
+        assert_eq!(strip_personal_prefix("meetings"), None);
+    }
+
+    #[test]
+    fn it_keeps_personal_keys_apart_per_owner() {
+        assert_eq!(
+            personal_alias_to_key("ada@example.com", "standup", &[]),
+            "rustlinks-personal/ada%40example.com/standup"
+        );
+        assert_eq!(
+            personal_alias_to_key("a/b", "standup", &[]),
+            "rustlinks-personal/a%2Fb/standup"
+        );
+    }
+
+    #[test]
+    fn it_leaves_aliases_alone_without_normalizations() {
+        assert_eq!(canonicalize_alias("On-Call", &[]), "On-Call");
+    }
+
+    #[test]
+    fn it_canonicalizes_case_and_separators() {
+        let normalizations = [AliasNormalization::Case, AliasNormalization::Separators];
+
+        for alias in ["OnCall", "on-call", "on_call", "ON-CALL"] {
+            assert_eq!(canonicalize_alias(alias, &normalizations), "oncall");
+        }
+    }
+
+    #[test]
+    fn it_canonicalizes_each_path_segment() {
+        let normalizations = [
+            AliasNormalization::Nfkc,
+            AliasNormalization::Case,
+            AliasNormalization::Separators,
+        ];
+        assert_eq!(
+            canonicalize_alias("Ｐａｙｍｅｎｔｓ/Run-Book", &normalizations),
+            "payments/runbook"
+        );
+    }
+
+    #[test]
+    fn it_keeps_characters_normalizing_to_slashes() {
+        let normalizations = [AliasNormalization::Nfkc, AliasNormalization::Case];
+
+        for alias in ["Ｏｎ／Ｃａｌｌ", "ｏｎ℅ｃａｌｌ", "ｏｎ/Ｃａｌｌ"] {
+            let canonical = canonicalize_alias(alias, &normalizations);
+            assert_eq!(
+                canonical.split('/').count(),
+                alias.split('/').count(),
+                "{} => {}",
+                alias,
+                canonical
+            );
+        }
+        assert_eq!(
+            canonicalize_alias("Ｏｎ／Ｃａｌｌ", &normalizations),
+            "on／call"
+        );
+    }
+}
@@ -1,25 +1,42 @@
 use std::{
     io::{Read, Seek, Write},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use chrono::Utc;
 use etcd_rs::{
-    proto::etcdserverpb::WatchCreateRequest as ProtoWatchCreateRequest, KeyRange, WatchCanceler,
-    WatchCreateRequest, WatchInbound, WatchOp, WatchStream,
+    proto::etcdserverpb::WatchCreateRequest as ProtoWatchCreateRequest, Client, KeyRange,
+    WatchCanceler, WatchCreateRequest, WatchInbound, WatchOp, WatchStream,
 };
 use tokio::{sync::Mutex, time::sleep};
 
 use crate::{
+    collection,
     errors::RustlinksError,
+    index::RustlinkIndex,
+    leader,
     state::{AppState, SerdeAppState},
-    util::{self, NAMESPACE},
+    trash,
+    util::{self, COLLECTIONS_NAMESPACE, NAMESPACE, TRASH_NAMESPACE},
 };
 
+/// The keys a watch is for
+#[derive(Clone, Copy)]
+enum Watched<'a> {
+    Shared,
+    /// The personal links of an owner
+    Personal(&'a str),
+    Collections,
+    Trash,
+}
+
 #[derive(Clone)]
 pub struct Worker {
     pub state: actix_web::web::Data<AppState>,
-    pub cancel: Arc<Mutex<Option<WatchCanceler>>>,
+    /// Watched for changes to the links, collections and trash
+    pub etcd_client: Arc<Client>,
+    pub cancel: Arc<Mutex<Vec<WatchCanceler>>>,
     pub sleep: Arc<Mutex<Option<()>>>,
 }
 
@@ -47,10 +64,42 @@ impl Worker {
                             Ok(disk_state) => {
                                 let mut rustlinks: tokio::sync::RwLockWriteGuard<
                                     '_,
-                                    std::collections::HashMap<String, crate::rustlink::Rustlink>,
+                                    crate::index::RustlinkIndex,
                                 > = self.state.rustlinks.write().await;
-                                rustlinks.extend(disk_state.rustlinks);
+                                // Re-canonicalize in case the configured normalizations
+                                // changed since the links were persisted
+                                rustlinks.extend(disk_state.rustlinks.into_iter().map(
+                                    |(alias, rustlink)| {
+                                        (
+                                            util::canonicalize_alias(
+                                                &alias,
+                                                &self.state.alias_normalization,
+                                            ),
+                                            rustlink,
+                                        )
+                                    },
+                                ));
                                 *self.state.revision.write().await = disk_state.revision;
+                                self.state.usage.extend(disk_state.usage);
+                                self.state
+                                    .collections
+                                    .write()
+                                    .await
+                                    .extend(disk_state.collections);
+
+                                // Only keep personal links this node is still configured
+                                // to sync
+                                let mut personal_rustlinks =
+                                    self.state.personal_rustlinks.write().await;
+                                personal_rustlinks.extend(
+                                    disk_state
+                                        .personal_rustlinks
+                                        .into_iter()
+                                        .filter(|(owner, _)| {
+                                            self.state.personal_owners.contains(owner)
+                                        })
+                                        .map(|(owner, rustlinks)| (owner, rustlinks.into())),
+                                );
                             }
                             Err(e) => {
                                 eprintln!("Failed to deserialize links file: {:?}", e);
@@ -67,16 +116,168 @@ impl Worker {
             }
         };
 
-        let mut stream: WatchStream;
+        let personal = self
+            .state
+            .personal_owners
+            .iter()
+            .map(|owner| self.sync_personal(owner));
+        futures::future::join5(
+            self.sync_shared(),
+            self.sync_collections(),
+            self.sync_trash(),
+            futures::future::join_all(personal),
+            self.purge_trash(),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Watch the shared links, from the last revision this node has seen
+    async fn sync_shared(&self) {
+        let start_revision = *self.state.revision.read().await;
+        let stream = self.watch(NAMESPACE, start_revision).await;
+        self.consume(stream, Watched::Shared).await;
+    }
+
+    /// Sync the collections. Like personal links, they're re-fetched in full
+    /// before watching for changes, rather than tracked by the shared
+    /// revision.
+    async fn sync_collections(&self) {
+        let start_revision = match self.state.store.get_prefix(COLLECTIONS_NAMESPACE).await {
+            Ok((values, revision)) => {
+                let mut collections = collection::Collections::new();
+
+                for value in values {
+                    if let Some(name) = value.key.strip_prefix(COLLECTIONS_NAMESPACE) {
+                        let _ = collection::apply_put(
+                            &mut collections,
+                            name,
+                            &value.value,
+                            value.mod_revision,
+                            &self.state.alias_normalization,
+                        );
+                    }
+                }
+                *self.state.collections.write().await = collections;
+                revision + 1
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch collections: {:?}", e);
+                0
+            }
+        };
+        let stream = self.watch(COLLECTIONS_NAMESPACE, start_revision).await;
+        self.consume(stream, Watched::Collections).await;
+    }
+
+    /// Sync the trash, re-fetched in full before watching for changes like the
+    /// collections, so that misses can tell whether a link was deleted without
+    /// a round trip to etcd
+    async fn sync_trash(&self) {
+        let start_revision = match self.state.store.get_prefix(TRASH_NAMESPACE).await {
+            Ok((values, revision)) => {
+                let mut trash = trash::Trash::new();
+
+                for value in values {
+                    if let Some(alias) = value.key.strip_prefix(TRASH_NAMESPACE) {
+                        let _ = trash::apply_put(
+                            &mut trash,
+                            alias,
+                            &value.value,
+                            &self.state.alias_normalization,
+                        );
+                    }
+                }
+                *self.state.trash.write().await = trash;
+                revision + 1
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch trash: {:?}", e);
+                0
+            }
+        };
+        let stream = self.watch(TRASH_NAMESPACE, start_revision).await;
+        self.consume(stream, Watched::Trash).await;
+    }
+
+    /// Purge links which have been in the trash for longer than the retention
+    /// period, every `trash::PURGE_INTERVAL`. Only the leader purges, so
+    /// nodes don't race each other for the same links.
+    async fn purge_trash(&self) {
+        if self.state.read_only {
+            return;
+        }
+        // Claims outlive the interval, so the leader renews its claim before
+        // it can lapse
+        let mut leadership = leader::Leadership::new(leader::node_id(), trash::PURGE_INTERVAL * 2);
+
+        loop {
+            let store = &*self.state.store;
+
+            match leadership.claim(store, Instant::now()).await {
+                Ok(true) => {
+                    let retention = self.state.trash_retention;
+
+                    match trash::purge(store, retention, Utc::now()).await {
+                        Ok(0) => {}
+                        Ok(purged) => println!("Purged {} link(s) from the trash", purged),
+                        Err(e) => eprintln!("Failed to purge trash: {:?}", e),
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to claim leadership: {:?}", e),
+            }
+            sleep(trash::PURGE_INTERVAL).await;
+        }
+    }
+
+    /// Sync the personal links of `owner`. These aren't included in the
+    /// shared revision, so they're re-fetched in full before watching for
+    /// changes.
+    async fn sync_personal(&self, owner: &str) {
+        let prefix = util::personal_prefix(owner);
+        let start_revision = match self.state.store.get_prefix(&prefix).await {
+            Ok((values, revision)) => {
+                let mut rustlinks = RustlinkIndex::default();
+
+                for value in values {
+                    if let Some(alias) = value.key.strip_prefix(&prefix) {
+                        let _ = rustlinks.apply_put(
+                            alias,
+                            &value.value,
+                            value.mod_revision,
+                            &self.state.alias_normalization,
+                        );
+                    }
+                }
+                self.state
+                    .personal_rustlinks
+                    .write()
+                    .await
+                    .insert(owner.to_string(), rustlinks);
+                revision + 1
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch personal links of {}: {:?}", owner, e);
+                0
+            }
+        };
+        let stream = self.watch(&prefix, start_revision).await;
+        self.consume(stream, Watched::Personal(owner)).await;
+    }
+
+    /// Start watching keys under `prefix`, retrying with backoff until etcd
+    /// accepts the watch
+    async fn watch(&self, prefix: &str, start_revision: i64) -> WatchStream {
         let mut backoff = 1;
 
         loop {
-            let range = KeyRange::prefix(NAMESPACE);
+            let range = KeyRange::prefix(prefix);
             let request = WatchCreateRequest {
                 proto: ProtoWatchCreateRequest {
                     key: range.key,
                     range_end: range.range_end,
-                    start_revision: self.state.revision.read().await.clone(),
+                    start_revision,
                     progress_notify: false,
                     filters: vec![],
                     prev_kv: false,
@@ -84,13 +285,12 @@ impl Worker {
                     watch_id: 0,
                 },
             };
-            let watch = self.state.etcd_client.watch(request).await;
+            let watch = self.etcd_client.watch(request).await;
 
             match watch {
-                Ok((s, c)) => {
-                    stream = s;
-                    *self.cancel.lock().await = Some(c);
-                    break;
+                Ok((stream, canceler)) => {
+                    self.cancel.lock().await.push(canceler);
+                    return stream;
                 }
                 Err(e) => {
                     eprint!("Failed to start etcd watch: {:?}, sleeping for {:?} seconds before retrying", e, backoff);
@@ -100,7 +300,11 @@ impl Worker {
                 }
             }
         }
+    }
 
+    /// Apply events from `stream` to whatever is `watched`, until the watch
+    /// ends
+    async fn consume(&self, mut stream: WatchStream, watched: Watched<'_>) {
         loop {
             println!("polling for etcd inbound events...");
             match stream.inbound().await {
@@ -108,29 +312,110 @@ impl Worker {
                     println!("received event: {:?}", resp);
 
                     let futs = resp.events.into_iter().map(|event| async move {
-                        let alias = util::key_to_alias(event.kv.key_str());
+                        let key_alias = match watched {
+                            Watched::Personal(owner) => event
+                                .kv
+                                .key_str()
+                                .strip_prefix(&util::personal_prefix(owner))
+                                .unwrap_or_default()
+                                .to_string(),
+                            Watched::Collections => event
+                                .kv
+                                .key_str()
+                                .strip_prefix(COLLECTIONS_NAMESPACE)
+                                .unwrap_or_default()
+                                .to_string(),
+                            Watched::Trash => event
+                                .kv
+                                .key_str()
+                                .strip_prefix(TRASH_NAMESPACE)
+                                .unwrap_or_default()
+                                .to_string(),
+                            Watched::Shared => util::key_to_alias(event.kv.key_str()),
+                        };
+                        let normalizations = &self.state.alias_normalization;
+                        let revision = event.kv.mod_revision;
 
                         match event.event_type {
                             etcd_rs::EventType::Put => {
                                 let value = event.kv.value;
-                                match serde_json::from_slice(&value) {
-                                    Ok(rustlink) => {
-                                        let mut rustlinks = self.state.rustlinks.write().await;
-                                        rustlinks.insert(alias, rustlink);
 
-                                        let mut revision = self.state.revision.write().await;
-                                        *revision = event.kv.mod_revision;
+                                match watched {
+                                    Watched::Personal(owner) => {
+                                        let mut personal =
+                                            self.state.personal_rustlinks.write().await;
+                                        personal.entry(owner.to_string()).or_default().apply_put(
+                                            &key_alias,
+                                            &value,
+                                            revision,
+                                            normalizations,
+                                        )
+                                    }
+                                    Watched::Collections => {
+                                        let mut collections = self.state.collections.write().await;
+                                        collection::apply_put(
+                                            &mut collections,
+                                            &key_alias,
+                                            &value,
+                                            revision,
+                                            normalizations,
+                                        )
+                                    }
+                                    Watched::Trash => {
+                                        let mut trash = self.state.trash.write().await;
+                                        trash::apply_put(
+                                            &mut trash,
+                                            &key_alias,
+                                            &value,
+                                            normalizations,
+                                        )
+                                    }
+                                    Watched::Shared => {
+                                        let mut rustlinks = self.state.rustlinks.write().await;
+                                        rustlinks.apply_put(
+                                            &key_alias,
+                                            &value,
+                                            revision,
+                                            normalizations,
+                                        )?;
+                                        *self.state.revision.write().await = revision;
                                         Ok(())
                                     }
-                                    Err(err) => Err(err),
                                 }
                             }
                             etcd_rs::EventType::Delete => {
-                                let mut rustlinks = self.state.rustlinks.write().await;
-                                rustlinks.remove(&alias);
+                                match watched {
+                                    Watched::Personal(owner) => {
+                                        let mut personal =
+                                            self.state.personal_rustlinks.write().await;
+
+                                        if let Some(rustlinks) = personal.get_mut(owner) {
+                                            rustlinks.apply_delete(&key_alias, normalizations);
+                                        }
+                                    }
+                                    Watched::Collections => {
+                                        let mut collections = self.state.collections.write().await;
+                                        collection::apply_delete(
+                                            &mut collections,
+                                            &key_alias,
+                                            normalizations,
+                                        );
+                                    }
+                                    Watched::Trash => {
+                                        let mut trash = self.state.trash.write().await;
+                                        trash::apply_delete(&mut trash, &key_alias, normalizations);
+                                    }
+                                    Watched::Shared => {
+                                        let mut rustlinks = self.state.rustlinks.write().await;
+                                        rustlinks.apply_delete(&key_alias, normalizations);
+                                        self.state.usage.remove(&util::canonicalize_alias(
+                                            &key_alias,
+                                            normalizations,
+                                        ));
 
-                                let mut revision = self.state.revision.write().await;
-                                *revision = event.kv.mod_revision;
+                                        *self.state.revision.write().await = revision;
+                                    }
+                                }
                                 Ok(())
                             }
                         }
@@ -166,7 +451,6 @@ impl Worker {
                 }
             }
         }
-        Ok(())
     }
 
     pub async fn stop(&self) -> Result<(), RustlinksError> {
@@ -175,13 +459,16 @@ impl Worker {
             drop(sleep);
         }
 
-        if let Some(canceler) = self.cancel.lock().await.take() {
+        let cancelers: Vec<WatchCanceler> = self.cancel.lock().await.drain(..).collect();
+
+        if cancelers.is_empty() {
+            println!("nothing to cancel");
+        }
+        for canceler in cancelers {
             canceler
                 .cancel()
                 .await
                 .or_else(|e| Err(RustlinksError::EtcdError(e)))?
-        } else {
-            println!("nothing to cancel");
         }
         Ok(())
     }